@@ -1,27 +1,70 @@
 use std::{
     env,
+    ops::Range,
     path::{Path, PathBuf},
-    thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use kronos::finder::Finder;
 use kronos::gen_funcs;
-use kronos::music_handler::MusicHandle;
-use kronos::queue::Queue;
+use kronos::history::History;
+use kronos::library;
+use kronos::library_browser::LibraryBrowser;
+use kronos::music_handler::{AbLoop, MusicHandle};
+use kronos::playlist;
+use kronos::playlist_manager::PlaylistManager;
+use kronos::queue::{Queue, RepeatMode};
+use kronos::search::{self, Search};
+use kronos::sleep_timer::{self, SleepTimer};
 use kronos::stateful_list::StatefulList;
 use kronos::stateful_table::StatefulTable;
+use crate::keymap::Keymap;
 use crate::state::{save_state, State};
 
-#[derive(Clone, Copy)]
+// how long before the end of a song the next one is decoded and appended to the sink
+const PRELOAD_TIME: Duration = Duration::from_secs(5);
+// going to the previous song this far into the current one restarts it instead
+const RESTART_AFTER: Duration = Duration::from_secs(3);
+
+#[derive(Clone, Copy, PartialEq)]
 pub enum InputMode {
     Browser,
     Queue,
+    Library,
     Controls,
+    /// Typing a search query for the browser or the queue.
+    Search,
+    /// Typing a query into the library wide finder.
+    Finder,
+    /// Asked whether to add a lot of songs to the queue at once.
+    Confirm,
+    /// Typing the name to save the queue as, or to rename a playlist to.
+    SavePlaylist,
+    /// Saved playlists and the songs in them.
+    Playlists,
+    /// Songs played this session.
+    History,
+}
+
+impl InputMode {
+    /// Every pane keys can be bound in. Keys typed while searching are taken as they are.
+    pub const ALL: [InputMode; 6] = [
+        InputMode::Browser,
+        InputMode::Queue,
+        InputMode::Playlists,
+        InputMode::Library,
+        InputMode::History,
+        InputMode::Controls,
+    ];
 }
 
 /// Represents the active tab state.
 #[derive(Debug, Clone, Copy)]
 pub enum AppTab {
     Music = 0,
+    Playlists,
+    Library,
+    History,
     Controls,
 }
 
@@ -29,26 +72,94 @@ impl AppTab {
     /// Get the next tab in the list.
     pub fn next(&self) -> Self {
         match self {
-            Self::Music => Self::Controls,
+            Self::Music => Self::Playlists,
+            Self::Playlists => Self::Library,
+            Self::Library => Self::History,
+            Self::History => Self::Controls,
             // Wrap around to the first tab.
             Self::Controls => Self::Music,
         }
     }
 }
 
+/// Whether playback goes on with the queue once the current song ends.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Stopping {
+    Off,
+    AfterCurrent,
+    /// The song it was set for ended, the queue waits until playback is started again.
+    Stopped,
+}
+
+impl Stopping {
+    // nothing is playing anymore, true if the next song should start
+    fn song_ended(&mut self) -> bool {
+        match self {
+            Stopping::Off => true,
+            Stopping::AfterCurrent | Stopping::Stopped => {
+                *self = Stopping::Stopped;
+                false
+            }
+        }
+    }
+
+    // playback was started by hand
+    fn resume(&mut self) {
+        if *self == Stopping::Stopped {
+            *self = Stopping::Off;
+        }
+    }
+}
+
 pub struct App<'a> {
     pub browser_items: StatefulList<String>,
     pub queue_items: Queue,
+    pub library: LibraryBrowser,
+    pub playlists: PlaylistManager,
+    pub history: History,
     pub control_table: StatefulTable<'a>,
     pub music_handle: MusicHandle,
     input_mode: InputMode,
     pub titles: Vec<&'a str>,
     pub active_tab: AppTab,
     pub last_visited_path: PathBuf,
+    search: Option<Search>,
+    // pane the search filters
+    search_pane: InputMode,
+    // query of the last finished search, repeated by search_next
+    last_search: Option<String>,
+    finder: Option<Finder>,
+    // pane focused before the finder opened
+    finder_pane: InputMode,
+    // folder depth and song count limits when adding songs to the queue
+    max_depth: usize,
+    confirm_above: usize,
+    // songs waiting for the user to confirm they should be queued
+    pending: Vec<PathBuf>,
+    // runs of the pending songs found in folders, put in track order once confirmed
+    pending_folders: Vec<Range<usize>>,
+    // whether they go in front of the queue
+    pending_next: bool,
+    // saved playlist waiting for the user to confirm it should be deleted
+    deleting: Option<String>,
+    confirm_pane: InputMode,
+    // name typed so far when saving the queue as a playlist
+    playlist_name: String,
+    save_pane: InputMode,
+    // saved playlist the typed name is for, rather than a new one
+    renaming: Option<String>,
+    // message shown over everything until the next key press
+    notice: Option<String>,
+    stopping: Stopping,
+    sleep_timer: SleepTimer,
+    // last song that couldn't be preloaded, so it isn't tried again every tick
+    unplayable: Option<PathBuf>,
+    // the current and next song, and whether they're from the same album
+    same_album: Option<(PathBuf, PathBuf, bool)>,
 }
 
 impl<'a> App<'a> {
-    pub fn new(initial_directory: Option<String>) -> Self {
+    pub fn new(initial_directory: Option<String>, keymap: &Keymap) -> Self {
         if let Some(path) = initial_directory {
             env::set_current_dir(&path).unwrap_or_else(|err| {
                 eprintln!("Could not set_current_dir to last_visited_path\n\tPath: {}\n\tError: {:?}", path, err);
@@ -58,25 +169,110 @@ impl<'a> App<'a> {
         Self {
             browser_items: StatefulList::with_items(gen_funcs::scan_and_filter_directory()),
             queue_items: Queue::with_items(),
-            control_table: StatefulTable::new(),
+            library: LibraryBrowser::new(),
+            history: History::new(),
+            playlists: PlaylistManager::new(
+                home::home_dir()
+                    .unwrap()
+                    .as_path()
+                    .join(".config/kronos/playlists"),
+            ),
+            control_table: StatefulTable::with_items(
+                vec!["Pane", "Keys", "Commands"],
+                keymap.controls(),
+            ),
             music_handle: MusicHandle::new(),
             input_mode: InputMode::Browser,
-            titles: vec!["Music", "Controls"],
+            titles: vec!["Music", "Playlists", "Library", "History", "Controls"],
             active_tab: AppTab::Music,
             last_visited_path: env::current_dir().unwrap(),
+            search: None,
+            search_pane: InputMode::Browser,
+            last_search: None,
+            finder: None,
+            finder_pane: InputMode::Browser,
+            max_depth: usize::MAX,
+            confirm_above: usize::MAX,
+            pending: Vec::new(),
+            pending_folders: Vec::new(),
+            pending_next: false,
+            deleting: None,
+            confirm_pane: InputMode::Browser,
+            playlist_name: String::new(),
+            save_pane: InputMode::Browser,
+            renaming: None,
+            notice: None,
+            stopping: Stopping::Off,
+            sleep_timer: SleepTimer::Off,
+            unplayable: None,
+            same_album: None,
+        }
+    }
+
+    // bring back the queue and song of the last session, paused where it was left.
+    // songs deleted in the meantime are skipped
+    pub fn restore_state(&mut self, state: State) {
+        if let Some(volume) = state.volume {
+            self.music_handle.set_volume(volume);
+        }
+        self.queue_items.set_repeat(state.repeat);
+        self.queue_items.set_shuffle_mode(state.shuffle);
+
+        for path in state.queue.into_iter().map(PathBuf::from) {
+            if path.is_file() {
+                self.queue_items.add(path);
+            }
+        }
+
+        if let Some(path) = state.current_song.map(PathBuf::from) {
+            if path.is_file() {
+                let position = Duration::from_millis(state.position_ms);
+                if let Err(err) = self.music_handle.load_paused(path, position) {
+                    self.notice = Some(err);
+                }
+            }
         }
     }
 
     pub fn save_state(self) {
+        let current_song = if self.music_handle.sink_empty() {
+            None
+        } else {
+            self.music_handle.current_song()
+        };
+
         save_state(State {
             last_visited_path: self.last_visited_path.to_str().map(String::from),
+            queue: self
+                .queue_items
+                .items()
+                .iter()
+                .filter_map(|path| path.to_str().map(String::from))
+                .collect(),
+            current_song: current_song.and_then(|path| path.to_str().map(String::from)),
+            position_ms: self.music_handle.time_played().as_millis() as u64,
+            volume: Some(self.music_handle.volume()),
+            repeat: self.queue_items.repeat(),
+            shuffle: self.queue_items.shuffle_mode(),
         }).unwrap_or_else(|error| {
             eprintln!("Error in save_state {}", error);
         });
+
+        library::save().unwrap_or_else(|error| {
+            eprintln!("Error saving library {}", error);
+        });
     }
 
+    // switch to the next tab and give its pane focus
     pub fn next(&mut self) {
         self.active_tab = self.active_tab.next();
+        self.input_mode = match self.active_tab {
+            AppTab::Music => InputMode::Browser,
+            AppTab::Playlists => InputMode::Playlists,
+            AppTab::Library => InputMode::Library,
+            AppTab::History => InputMode::History,
+            AppTab::Controls => InputMode::Controls,
+        };
     }
 
     pub fn input_mode(&self) -> InputMode {
@@ -87,6 +283,462 @@ impl<'a> App<'a> {
         self.input_mode = in_mode
     }
 
+    // start filtering the focused pane with a search query
+    pub fn start_search(&mut self) {
+        let items = self.search_items(self.input_mode);
+        self.search = Some(Search::new(self.selected_in(self.input_mode), &items));
+        self.search_pane = self.input_mode;
+        self.input_mode = InputMode::Search;
+    }
+
+    // keys typed while searching. enter jumps to the highlighted match, escape goes back to
+    // where the search started
+    pub fn search_input(&mut self, key: KeyEvent) {
+        let Some(mut search) = self.search.take() else {
+            return;
+        };
+        let pane = self.search_pane;
+        let items = self.search_items(pane);
+
+        match key.code {
+            KeyCode::Esc => {
+                self.select_in(pane, search.previous());
+                self.input_mode = pane;
+                return;
+            }
+            KeyCode::Enter => {
+                self.select_in(pane, search.selected().or(search.previous()));
+                self.last_search = Some(search.query().to_string());
+                self.input_mode = pane;
+                return;
+            }
+            KeyCode::Backspace => search.pop(&items),
+            KeyCode::Down => search.next_match(),
+            KeyCode::Up => search.previous_match(),
+            KeyCode::Char(c) if is_text(&key) => search.push(c, &items),
+            _ => {}
+        }
+
+        self.search = Some(search);
+    }
+
+    // the queue can change while it's searched, as songs finish playing or get drawn by shuffle,
+    // so the matches are worked out again before they're shown or picked
+    pub fn update_search(&mut self) {
+        if self.search.is_some() {
+            let items = self.search_items(self.search_pane);
+            if let Some(search) = &mut self.search {
+                search.refresh(&items);
+            }
+        }
+    }
+
+    // jump to the next item in the focused pane matching the last search
+    pub fn search_next(&mut self, forward: bool) {
+        let Some(query) = &self.last_search else {
+            return;
+        };
+        let items = self.search_items(self.input_mode);
+        let from = self.selected_in(self.input_mode);
+
+        if let Some(i) = search::find_next(query, &items, from, forward) {
+            self.select_in(self.input_mode, Some(i));
+        }
+    }
+
+    // the search in progress, if it's filtering `pane`
+    pub fn searching(&self, pane: InputMode) -> Option<&Search> {
+        match self.input_mode {
+            InputMode::Search if self.search_pane == pane => self.search.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// How many levels of subfolders adding a folder reaches into, and how many songs can be
+    /// added at once without asking.
+    pub fn set_enqueue_limits(&mut self, max_depth: usize, confirm_above: usize) {
+        self.max_depth = max_depth;
+        self.confirm_above = confirm_above;
+    }
+
+    // queue the items marked in the browser, or the selected one. in front of everything else
+    // queued when `next`, so they play next
+    pub fn add_selection(&mut self, next: bool) {
+        let (songs, folders) = self.browser_selection();
+        self.browser_items.clear_marks();
+        self.enqueue_scanned(songs, folders, next);
+    }
+
+    // songs of the items marked in the browser, or of the selected one, and which runs of them
+    // were found in folders
+    fn browser_selection(&mut self) -> (Vec<PathBuf>, Vec<Range<usize>>) {
+        let current_dir = env::current_dir().unwrap();
+        let paths: Vec<PathBuf> = if self.browser_items.has_marks() {
+            let marked = self.browser_items.marked();
+            marked
+                .into_iter()
+                .map(|item| current_dir.join(item))
+                .collect()
+        } else {
+            vec![self.selected_item()]
+        };
+
+        let mut songs = Vec::new();
+        let mut folders = Vec::new();
+        for path in paths {
+            if path.is_dir() {
+                let start = songs.len();
+                songs.extend(gen_funcs::bulk_add(&path, self.max_depth));
+                folders.push(start..songs.len());
+            } else {
+                songs.extend(self.songs_at(&path));
+            }
+        }
+        (songs, folders)
+    }
+
+    // a song or a playlist's songs
+    fn songs_at(&mut self, path: &Path) -> Vec<PathBuf> {
+        if playlist::is_playlist(path) {
+            self.playlist_songs(path)
+        } else {
+            vec![path.to_path_buf()]
+        }
+    }
+
+    // queue songs, asking first when there are a lot of them
+    pub fn enqueue(&mut self, songs: Vec<PathBuf>) {
+        self.enqueue_at(songs, false);
+    }
+
+    // queue songs at the back, or at the front when `next`
+    pub fn enqueue_at(&mut self, songs: Vec<PathBuf>, next: bool) {
+        self.enqueue_scanned(songs, Vec::new(), next);
+    }
+
+    // the tags of songs found in `folders` are only read once it's certain they're queued, as
+    // that's slow for big folders
+    fn enqueue_scanned(&mut self, songs: Vec<PathBuf>, folders: Vec<Range<usize>>, next: bool) {
+        if songs.len() > self.confirm_above {
+            self.pending = songs;
+            self.pending_folders = folders;
+            self.pending_next = next;
+            self.confirm_pane = self.input_mode;
+            self.input_mode = InputMode::Confirm;
+        } else {
+            self.queue_songs(songs, folders, next);
+        }
+    }
+
+    fn queue_songs(&mut self, mut songs: Vec<PathBuf>, folders: Vec<Range<usize>>, next: bool) {
+        for folder in folders {
+            gen_funcs::track_order(&mut songs[folder]);
+        }
+        if next {
+            self.queue_items.add_next(songs);
+        } else {
+            self.queue_items.extend(songs);
+        }
+    }
+
+    // answer to whether the pending songs should be queued or the playlist deleted
+    pub fn confirm_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Enter => {
+                if let Some(name) = self.deleting.take() {
+                    if let Err(err) = self.playlists.delete(&name) {
+                        self.notice = Some(format!("Could not delete {name}: {err}"));
+                    }
+                }
+                let songs = std::mem::take(&mut self.pending);
+                let folders = std::mem::take(&mut self.pending_folders);
+                self.queue_songs(songs, folders, self.pending_next);
+            }
+            KeyCode::Char('n') | KeyCode::Esc => {
+                self.deleting = None;
+                self.pending.clear();
+                self.pending_folders.clear();
+            }
+            _ => return,
+        }
+        self.input_mode = self.confirm_pane;
+    }
+
+    pub fn pending(&self) -> &[PathBuf] {
+        &self.pending
+    }
+
+    pub fn deleting(&self) -> Option<&str> {
+        self.deleting.as_deref()
+    }
+
+    // replace the queue with the songs of the selected saved playlist, what's playing keeps
+    // playing
+    pub fn load_playlist(&mut self) {
+        if let Some(songs) = self.saved_playlist_songs() {
+            // undone in one go with the clearing
+            self.queue_items.clear();
+            for song in songs {
+                self.queue_items.add(song);
+            }
+        }
+    }
+
+    // queue the songs of the selected saved playlist. the user put them there, so there's no
+    // asking about how many there are
+    pub fn append_playlist(&mut self) {
+        if let Some(songs) = self.saved_playlist_songs() {
+            self.queue_items.extend(songs);
+        }
+    }
+
+    // songs of the selected saved playlist that still exist
+    fn saved_playlist_songs(&mut self) -> Option<Vec<PathBuf>> {
+        let name = self.playlists.selected_name()?.to_string();
+
+        match self.playlists.entries(&name) {
+            Ok(entries) => {
+                let missing = playlist::missing(&entries).len();
+                if missing > 0 {
+                    self.notice = Some(format!("{missing} songs in {name} no longer exist"));
+                }
+                let songs = entries.into_iter().map(|entry| entry.path);
+                Some(songs.filter(|path| path.is_file()).collect())
+            }
+            Err(err) => {
+                self.notice = Some(format!("Could not read {name}: {err}"));
+                None
+            }
+        }
+    }
+
+    // ask before deleting the selected saved playlist
+    pub fn delete_playlist(&mut self) {
+        if let Some(name) = self.playlists.selected_name() {
+            self.deleting = Some(name.to_string());
+            self.confirm_pane = self.input_mode;
+            self.input_mode = InputMode::Confirm;
+        }
+    }
+
+    // ask for a new name for the selected saved playlist
+    pub fn rename_playlist(&mut self) {
+        if let Some(name) = self.playlists.selected_name() {
+            self.renaming = Some(name.to_string());
+            self.playlist_name = name.to_string();
+            self.save_pane = self.input_mode;
+            self.input_mode = InputMode::SavePlaylist;
+        }
+    }
+
+    pub fn remove_from_playlist(&mut self) {
+        if let Err(err) = self.playlists.remove_song() {
+            self.notice = Some(format!("Could not change the playlist: {err}"));
+        }
+    }
+
+    // move the selected song of the playlist being edited to `to`
+    pub fn move_in_playlist(&mut self, to: usize) {
+        if let Err(err) = self.playlists.move_song(to) {
+            self.notice = Some(format!("Could not change the playlist: {err}"));
+        }
+    }
+
+    // the songs of a playlist, leaving out those that can't be found
+    fn playlist_songs(&mut self, path: &Path) -> Vec<PathBuf> {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        match playlist::read(path) {
+            Ok(entries) => {
+                let missing = playlist::missing(&entries).len();
+                if missing > 0 {
+                    self.notice = Some(format!("{missing} songs in {name} no longer exist"));
+                }
+
+                entries
+                    .into_iter()
+                    .map(|entry| entry.path)
+                    .filter(|path| path.is_file())
+                    .collect()
+            }
+            Err(err) => {
+                self.notice = Some(format!("Could not read {name}: {err}"));
+                Vec::new()
+            }
+        }
+    }
+
+    pub fn notice(&self) -> Option<&str> {
+        self.notice.as_deref()
+    }
+
+    pub fn dismiss_notice(&mut self) {
+        self.notice = None;
+    }
+
+    // ask for a name to save the queue under
+    pub fn start_save_playlist(&mut self) {
+        self.renaming = None;
+        self.playlist_name.clear();
+        self.save_pane = self.input_mode;
+        self.input_mode = InputMode::SavePlaylist;
+    }
+
+    // keys typed while naming the playlist. enter saves it in the browser's folder, or with the
+    // saved playlists when started from their tab
+    pub fn save_playlist_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => self.renaming = None,
+            KeyCode::Enter if !self.playlist_name.trim().is_empty() => self.save_playlist(),
+            KeyCode::Backspace => {
+                self.playlist_name.pop();
+                return;
+            }
+            KeyCode::Char(c) if is_text(&key) => {
+                self.playlist_name.push(c);
+                return;
+            }
+            _ => return,
+        }
+        self.input_mode = self.save_pane;
+    }
+
+    pub fn playlist_name(&self) -> &str {
+        &self.playlist_name
+    }
+
+    pub fn renaming(&self) -> Option<&str> {
+        self.renaming.as_deref()
+    }
+
+    // whether only marked songs are saved rather than the whole queue
+    pub fn saving_marked(&self) -> bool {
+        (self.save_pane == InputMode::Browser && self.browser_items.has_marks())
+            || self.queue_items.has_marks()
+    }
+
+    fn save_playlist(&mut self) {
+        let songs = if self.save_pane == InputMode::Browser && self.browser_items.has_marks() {
+            let (mut songs, folders) = self.browser_selection();
+            for folder in folders {
+                gen_funcs::track_order(&mut songs[folder]);
+            }
+            self.browser_items.clear_marks();
+            songs
+        } else if self.queue_items.has_marks() {
+            let songs = self.queue_items.marked();
+            self.queue_items.clear_marks();
+            songs
+        } else {
+            self.queue_items.items().iter().cloned().collect()
+        };
+        let name = self.playlist_name.trim();
+
+        if self.save_pane == InputMode::Playlists {
+            let saved = match self.renaming.take() {
+                Some(old_name) => self.playlists.rename(&old_name, name),
+                None => self.playlists.save(name, &songs),
+            };
+            if let Err(err) = saved {
+                self.notice = Some(format!("Could not save {name}: {err}"));
+            }
+            return;
+        }
+
+        let name = if playlist::is_playlist(Path::new(name)) {
+            name.to_string()
+        } else {
+            format!("{name}.m3u8")
+        };
+        let path = env::current_dir().unwrap().join(&name);
+
+        match playlist::write(&path, &songs) {
+            // show the new playlist in the browser
+            Ok(()) => {
+                self.browser_items =
+                    StatefulList::with_items(gen_funcs::scan_and_filter_directory());
+                self.browser_items.select_by_path(&path);
+            }
+            Err(err) => self.notice = Some(format!("Could not save {name}: {err}")),
+        }
+    }
+
+    // pop up the finder over whatever pane is focused
+    pub fn open_finder(&mut self) {
+        self.finder = Some(Finder::new(self.library.songs()));
+        self.finder_pane = self.input_mode;
+        self.input_mode = InputMode::Finder;
+    }
+
+    // keys typed into the finder. enter plays the highlighted song and closes the finder, tab
+    // queues it and leaves the finder open for more
+    pub fn finder_input(&mut self, key: KeyEvent) {
+        let Some(finder) = &mut self.finder else {
+            return;
+        };
+        let songs = self.library.songs();
+        let selected = finder.selected().map(|i| songs[i].0.clone());
+
+        match key.code {
+            KeyCode::Esc => self.close_finder(),
+            KeyCode::Enter => {
+                if let Some(path) = selected {
+                    self.play(path);
+                }
+                self.close_finder();
+            }
+            KeyCode::Tab => {
+                if let Some(path) = selected {
+                    self.enqueue(vec![path]);
+                }
+            }
+            KeyCode::Backspace => finder.pop(songs),
+            KeyCode::Down => finder.next(),
+            KeyCode::Up => finder.previous(),
+            KeyCode::Char(c) if is_text(&key) => finder.push(c, songs),
+            _ => {}
+        }
+    }
+
+    pub fn finder(&self) -> Option<&Finder> {
+        self.finder.as_ref()
+    }
+
+    fn close_finder(&mut self) {
+        self.finder = None;
+        self.input_mode = self.finder_pane;
+    }
+
+    // items of a pane as they're displayed, which is what searches match against
+    fn search_items(&self, pane: InputMode) -> Vec<String> {
+        match pane {
+            InputMode::Queue => self
+                .queue_items
+                .items()
+                .iter()
+                .map(|i| gen_funcs::audio_display(i))
+                .collect(),
+            _ => self.browser_items.items().clone(),
+        }
+    }
+
+    fn selected_in(&self, pane: InputMode) -> Option<usize> {
+        match pane {
+            InputMode::Queue => self.queue_items.state().selected(),
+            _ => self.browser_items.state().selected(),
+        }
+    }
+
+    fn select_in(&mut self, pane: InputMode, i: Option<usize>) {
+        match (pane, i) {
+            (InputMode::Queue, Some(i)) if i < self.queue_items.length() => {
+                self.queue_items.select(i)
+            }
+            (InputMode::Queue, _) => self.queue_items.unselect(),
+            (_, Some(i)) => self.browser_items.select(i),
+            (_, None) => self.browser_items.unselect(),
+        }
+    }
+
     pub fn current_song(&self) -> String {
         if self.music_handle.sink_empty() && self.queue_items.is_empty() {
             "CURRENT SONG".to_string()
@@ -104,8 +756,11 @@ impl<'a> App<'a> {
             env::set_current_dir(join).unwrap();
             self.browser_items = StatefulList::with_items(gen_funcs::scan_and_filter_directory());
             self.browser_items.next();
+        } else if playlist::is_playlist(&join) {
+            let songs = self.playlist_songs(&join);
+            self.enqueue(songs);
         } else {
-            self.music_handle.play(join);
+            self.play(join);
         }
     }
 
@@ -116,27 +771,227 @@ impl<'a> App<'a> {
         self.browser_items.select_by_path(&self.last_visited_path);
     }
 
-    // if queue has items and nothing playing, auto play
+    // note the song that's playing in the history once it starts
+    pub fn record_history(&mut self) {
+        if self.music_handle.sink_empty() {
+            return;
+        }
+        if let Some(path) = self.music_handle.current_song() {
+            self.history.record(path);
+        }
+    }
+
+    // start the current song over, or when it only just started go back to the song before it.
+    // the current song goes back in front of the queue
+    pub fn play_previous(&mut self) {
+        let playing = !self.music_handle.sink_empty();
+        if playing && (self.music_handle.time_played() > RESTART_AFTER || self.history.len() < 2) {
+            self.music_handle.seek_to(Duration::ZERO);
+            return;
+        }
+
+        // the last song in the history is the one playing, or the one that just finished
+        if playing {
+            if let Some(current) = self.history.pop() {
+                self.queue_items.add_again(current);
+            }
+        }
+        if let Some(previous) = self.history.pop() {
+            self.play(previous);
+        }
+    }
+
+    pub fn toggle_stop_after_current(&mut self) {
+        self.stopping = match self.stopping {
+            // nothing is playing, so there's nothing to stop after
+            Stopping::Off if !self.music_handle.sink_empty() => Stopping::AfterCurrent,
+            Stopping::AfterCurrent => Stopping::Off,
+            stopping => stopping,
+        };
+    }
+
+    pub fn stop_after_current(&self) -> bool {
+        self.stopping == Stopping::AfterCurrent
+    }
+
+    // after stopping at the end of a song, starts the queue again
+    pub fn play_pause(&mut self) {
+        if self.stopping == Stopping::Stopped {
+            self.stopping.resume();
+            return;
+        }
+        self.music_handle.play_pause();
+    }
+
+    pub fn cycle_sleep_timer(&mut self) {
+        let queue_ends = self.queue_items.repeat() == RepeatMode::Off;
+        self.sleep_timer = self.sleep_timer.next(queue_ends);
+        self.music_handle.set_attenuation(1.);
+    }
+
+    pub fn sleep_timer(&self) -> SleepTimer {
+        self.sleep_timer
+    }
+
+    /// Time left until the sleep timer goes off, None when it's off.
+    pub fn sleep_left(&self) -> Option<Duration> {
+        match self.sleep_timer {
+            SleepTimer::Off => None,
+            SleepTimer::After { end, .. } => Some(end.saturating_duration_since(Instant::now())),
+            SleepTimer::EndOfQueue if self.music_handle.sink_empty() => Some(Duration::ZERO),
+            SleepTimer::EndOfQueue => {
+                Some(self.music_handle.time_remaining() + self.queue_items.duration())
+            }
+        }
+    }
+
+    // fade out over the last minute of the sleep timer, then pause
+    pub fn update_sleep_timer(&mut self) {
+        // repeat was turned on since, songs would keep coming back and it would never go off
+        if self.sleep_timer == SleepTimer::EndOfQueue
+            && self.queue_items.repeat() != RepeatMode::Off
+        {
+            self.sleep_timer = SleepTimer::Off;
+            self.music_handle.set_attenuation(1.);
+            self.notice = Some("The queue doesn't end while repeating, sleep timer off".into());
+            return;
+        }
+
+        let Some(left) = self.sleep_left() else {
+            return;
+        };
+
+        if left.is_zero() {
+            if !self.music_handle.sink_empty() && !self.music_handle.is_paused() {
+                self.music_handle.play_pause();
+            }
+            self.sleep_timer = SleepTimer::Off;
+            self.music_handle.set_attenuation(1.);
+        } else {
+            let fade = left.as_secs_f32() / sleep_timer::FADE_OUT.as_secs_f32();
+            self.music_handle.set_attenuation(fade.min(1.));
+        }
+    }
+
+    // if queue has items and nothing playing, auto play. the head of the queue is preloaded
+    // near the end of the current song so the two play back to back without a gap
     pub fn auto_play(&mut self) {
-        thread::sleep(Duration::from_millis(250));
-        if self.music_handle.sink_empty() && !self.queue_items.is_empty() {
-            self.music_handle.set_time_played(0);
-            self.music_handle.play(self.queue_items.pop());
+        if let Some(err) = self.music_handle.take_error() {
+            self.notice = Some(err);
+        }
+        let repeat_one = self.queue_items.repeat() == RepeatMode::One;
+
+        // the preloaded song took over, it's no longer queued
+        if let Some(path) = self.music_handle.advance() {
+            if !repeat_one && self.queue_items.items().front() == Some(&path) {
+                self.queue_items.pop();
+            }
+        }
+
+        if self.music_handle.sink_empty() {
+            if self.queue_items.is_empty() {
+                // nothing was held back, songs queued later start on their own
+                self.stopping = Stopping::Off;
+            } else if self.stopping.song_ended() {
+                let next = self.queue_items.pop();
+                self.play(next);
+            }
+            return;
+        }
+
+        let Some(current) = self.music_handle.current_song().cloned() else {
+            return;
+        };
+        // repeating a song is just preloading it behind itself
+        let next = if self.stopping != Stopping::Off {
+            None
+        } else if repeat_one {
+            Some(current.clone())
+        } else {
+            self.queue_items.upcoming().cloned()
+        };
+
+        // queue changed since the song was preloaded
+        let preloaded = self.music_handle.next_song();
+        if preloaded.is_some() && preloaded != next.as_ref() {
+            self.music_handle.cancel_next();
+        }
+        let Some(next) = next else {
+            return;
+        };
+        if self.unplayable.as_ref() == Some(&next) {
+            return;
+        }
+        // a looping song doesn't end, so nothing follows it yet
+        if matches!(self.music_handle.ab_loop(), AbLoop::Looping { .. }) {
+            return;
+        }
+
+        let remaining = self.music_handle.time_remaining();
+        if remaining > PRELOAD_TIME.max(self.music_handle.crossfade()) {
+            return;
+        }
+
+        // songs from the same album keep their gapless transitions, and songs too short to
+        // fade in and out again are left alone
+        let crossfade = self.music_handle.crossfade();
+        if crossfade.is_zero()
+            || self.music_handle.song_length() < crossfade * 2
+            || self.same_album(&current, &next)
+        {
+            if remaining <= PRELOAD_TIME {
+                if let Err(err) = self.music_handle.preload(next.clone()) {
+                    self.skip_unplayable(&next, err);
+                }
+            }
+        } else if remaining <= crossfade {
+            let next = self.queue_items.pop();
+            if let Err(err) = self.music_handle.crossfade_to(next.clone()) {
+                self.skip_unplayable(&next, err);
+            }
         }
     }
 
-    // if playing and
-    pub fn song_progress(&mut self) -> f64 {
-        // edge case if nothing queued or playing
-        if self.music_handle.sink_empty() && self.queue_items.is_empty() {
-            0.0
+    /// Start playing `path` right away. If it can't be played it's skipped, saying why.
+    pub fn play(&mut self, path: PathBuf) {
+        self.stopping.resume();
+        if let Err(err) = self.music_handle.play(path.clone()) {
+            self.skip_unplayable(&path, err);
+        }
+    }
+
+    // reads the tags of both songs only the first time it's asked about them, as it's asked on
+    // every tick while the current song ends
+    fn same_album(&mut self, current: &Path, next: &Path) -> bool {
+        match &self.same_album {
+            Some((a, b, same)) if a == current && b == next => *same,
+            _ => {
+                let same = gen_funcs::same_album(current, next);
+                self.same_album = Some((current.to_path_buf(), next.to_path_buf(), same));
+                same
+            }
+        }
+    }
 
+    // take a song that can't be played out of the queue, copies sent round again by repeat too,
+    // and say why
+    fn skip_unplayable(&mut self, path: &Path, err: String) {
+        self.unplayable = Some(path.to_path_buf());
+        self.queue_items.forget(path);
+        self.notice = Some(format!("{err}, skipped it"));
+    }
+
+    pub fn song_progress(&self) -> f64 {
         // if something playing, calculate progress
-        } else if !self.music_handle.sink_empty() {
-            f64::clamp(self.music_handle.time_played() as f64 / self.music_handle.song_length() as f64, 0.0, 1.0)
-        // if nothing playing keep rolling
+        if !self.music_handle.sink_empty() {
+            f64::clamp(
+                self.music_handle.time_played().as_secs_f64()
+                    / self.music_handle.song_length().as_secs_f64(),
+                0.0,
+                1.0,
+            )
+        // edge case if nothing queued or playing
         } else {
-            self.auto_play();
             0.0
         }
     }
@@ -152,3 +1007,35 @@ impl<'a> App<'a> {
         }
     }
 }
+
+// ctrl and alt chords typed into a query are shortcuts, not text
+fn is_text(key: &KeyEvent) -> bool {
+    !key.modifiers
+        .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queue_stays_stopped_after_the_song() {
+        let mut stopping = Stopping::AfterCurrent;
+        assert!(!stopping.song_ended());
+        // later ticks find nothing playing either
+        for _ in 0..3 {
+            assert!(!stopping.song_ended());
+        }
+        assert_eq!(stopping, Stopping::Stopped);
+
+        stopping.resume();
+        assert!(stopping.song_ended());
+    }
+
+    #[test]
+    fn resuming_keeps_a_pending_stop() {
+        let mut stopping = Stopping::AfterCurrent;
+        stopping.resume();
+        assert_eq!(stopping, Stopping::AfterCurrent);
+    }
+}
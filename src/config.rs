@@ -1,5 +1,6 @@
-use std::fs;
+use std::{collections::HashMap, fs, path::PathBuf, time::Duration};
 
+use kronos::replaygain::ReplayGainMode;
 use serde::{Deserialize, Serialize};
 use tui::style::Color;
 
@@ -16,11 +17,41 @@ struct Layout {
     progress_bar: Option<u16>,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+struct Playback {
+    crossfade_ms: Option<u64>,
+    replaygain: Option<String>,
+    replaygain_preamp: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Library {
+    path: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Queue {
+    max_depth: Option<usize>,
+    confirm_above: Option<usize>,
+}
+
+// an action can be bound to a single key or a list of them
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(untagged)]
+enum Keys {
+    One(String),
+    Many(Vec<String>),
+}
+
 // for tables
 #[derive(Serialize, Deserialize, Debug)]
 struct ConfigToml {
     theme: Option<Theme>,
     layout: Option<Layout>,
+    playback: Option<Playback>,
+    library: Option<Library>,
+    queue: Option<Queue>,
+    keys: Option<HashMap<String, Keys>>,
 }
 
 // everything
@@ -31,6 +62,13 @@ pub struct Config {
     highlight_foreground: Color,
     highlight_background: Color,
     progress_bar: u16,
+    crossfade: Duration,
+    replaygain: ReplayGainMode,
+    replaygain_preamp: f32,
+    keys: HashMap<String, Vec<String>>,
+    library_path: PathBuf,
+    max_depth: usize,
+    confirm_above: usize,
 }
 
 impl Default for Config {
@@ -60,13 +98,21 @@ impl Config {
             }
         }
 
+        Self::from_toml(&content)
+    }
+
+    fn from_toml(content: &str) -> Self {
         // convert toml file to serialized data
-        let config_toml: ConfigToml = toml::from_str(&content).unwrap_or_else(|_| {
+        let config_toml: ConfigToml = toml::from_str(content).unwrap_or_else(|_| {
             // if config file not found, set defaults
             eprintln!("FAILED TO CREATE CONFIG OBJECT FROM FILE");
             ConfigToml {
                 theme: None,
                 layout: None,
+                playback: None,
+                library: None,
+                queue: None,
+                keys: None,
             }
         });
 
@@ -98,7 +144,7 @@ impl Config {
                         "white" => Color::White,
                         _ => {
                             let colors: Vec<u8> = rgb.unwrap()
-                            .split(|i| i == ',')
+                            .split(',')
                             .map(|i| i.to_string().trim().parse().expect("Couldn't read RGB Values. Make sure each value is between 0 & 255"))
                             .collect();
 
@@ -133,41 +179,70 @@ impl Config {
             None => 35,
         };
 
+        let playback = config_toml.playback.unwrap_or(Playback {
+            crossfade_ms: None,
+            replaygain: None,
+            replaygain_preamp: None,
+        });
+
+        // 0 turns crossfade off, songs play back to back
+        let crossfade_ms = playback.crossfade_ms.unwrap_or(0);
+
+        let replaygain = match playback.replaygain {
+            Some(mode) => ReplayGainMode::from_name(&mode).unwrap_or_else(|| {
+                eprintln!("Unknown replaygain mode {mode}, expected off, track or album");
+                ReplayGainMode::Off
+            }),
+            None => ReplayGainMode::Off,
+        };
+        let replaygain_preamp = playback.replaygain_preamp.unwrap_or(0.);
+
+        // folder the library tab is built from, ~ stands for the home folder
+        let home = home::home_dir().unwrap();
+        let library_path = match config_toml.library.and_then(|library| library.path) {
+            Some(path) => match path.strip_prefix("~/") {
+                Some(path) => home.join(path),
+                None => PathBuf::from(path),
+            },
+            None => home.join("Music"),
+        };
+
+        // adding a folder takes songs from all its subfolders unless limited, and asks first
+        // when that's more than a few hundred
+        let queue = config_toml.queue.unwrap_or(Queue {
+            max_depth: None,
+            confirm_above: None,
+        });
+        let max_depth = queue.max_depth.unwrap_or(usize::MAX);
+        let confirm_above = queue.confirm_above.unwrap_or(500);
+
+        // checked against the known actions when the keymap is built
+        let keys = config_toml
+            .keys
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(action, keys)| match keys {
+                Keys::One(key) => (action, vec![key]),
+                Keys::Many(keys) => (action, keys),
+            })
+            .collect();
+
         Self {
-            // quit: quit, // gathered from above
-            // play_pause: play_pause,
-            // skip: skip,
-            // queue_add: queue_add,
-            // queue_remove: queue_remove,
             foreground,
             background,
             highlight_foreground,
             highlight_background,
             progress_bar,
+            crossfade: Duration::from_millis(crossfade_ms),
+            replaygain,
+            replaygain_preamp,
+            keys,
+            library_path,
+            max_depth,
+            confirm_above,
         }
     }
 
-    // pub fn quit_key(&self) -> KeyCode {
-
-    //     KeyCode::Char(self.quit)
-    // }
-
-    // pub fn play_pause_key(&self) -> char {
-    //     self.play_pause
-    // }
-
-    // pub fn skip_key(&self) -> char {
-    //     self.skip
-    // }
-
-    // pub fn queue_add_key(&self) -> char {
-    //     self.queue_add
-    // }
-
-    // pub fn queue_remove_key(&self) -> char {
-    //     self.queue_remove
-    // }
-
     pub fn foreground(&self) -> Color {
         self.foreground
     }
@@ -187,4 +262,55 @@ impl Config {
     pub fn progress_bar(&self) -> u16 {
         self.progress_bar
     }
+
+    pub fn crossfade(&self) -> Duration {
+        self.crossfade
+    }
+
+    pub fn replaygain(&self) -> ReplayGainMode {
+        self.replaygain
+    }
+
+    pub fn replaygain_preamp(&self) -> f32 {
+        self.replaygain_preamp
+    }
+
+    pub fn keys(&self) -> &HashMap<String, Vec<String>> {
+        &self.keys
+    }
+
+    pub fn library_path(&self) -> PathBuf {
+        self.library_path.clone()
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    pub fn confirm_above(&self) -> usize {
+        self.confirm_above
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keymap::Keymap;
+
+    #[test]
+    fn readme_keys_example_is_valid() {
+        let readme = include_str!("../README.md");
+        let example = readme
+            .split("```toml")
+            .skip(1)
+            .filter_map(|block| block.split("```").next())
+            .find(|block| block.contains("[keys]"))
+            .unwrap();
+        let cfg = Config::from_toml(example);
+
+        assert!(!cfg.keys().is_empty());
+        if let Err(err) = Keymap::new(cfg.keys()) {
+            panic!("{err}");
+        }
+    }
 }
@@ -1,6 +1,17 @@
 pub mod constants;
+pub mod crossfade;
+pub mod decoder;
+pub mod finder;
 pub mod gen_funcs;
+pub mod history;
+pub mod library;
+pub mod library_browser;
 pub mod music_handler;
+pub mod playlist;
+pub mod playlist_manager;
 pub mod queue;
+pub mod replaygain;
+pub mod search;
+pub mod sleep_timer;
 pub mod stateful_list;
 pub mod stateful_table;
@@ -0,0 +1,128 @@
+use std::{f32::consts::FRAC_PI_2, time::Duration};
+
+/// Volume envelope applied to a song while it crossfades with its neighbour.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fade {
+    /// Rise from silence over the first `length` of the song.
+    In { length: Duration },
+    /// Drop to silence over `length`, starting `from` into the song.
+    Out { from: Duration, length: Duration },
+}
+
+impl Fade {
+    /// Gain to apply when the song is `elapsed` in.
+    pub fn gain(&self, elapsed: Duration) -> f32 {
+        match *self {
+            Fade::In { length } => fade_in_gain(progress(elapsed, length)),
+            Fade::Out { from, length } => {
+                fade_out_gain(progress(elapsed.saturating_sub(from), length))
+            }
+        }
+    }
+
+    /// Whether a fade out has reached silence.
+    pub fn finished(&self, elapsed: Duration) -> bool {
+        match *self {
+            Fade::In { .. } => false,
+            Fade::Out { from, length } => elapsed >= from + length,
+        }
+    }
+}
+
+// how far into the fade we are, 0 at the start and 1 once it's done
+fn progress(elapsed: Duration, length: Duration) -> f32 {
+    if length.is_zero() {
+        1.
+    } else {
+        (elapsed.as_secs_f32() / length.as_secs_f32()).clamp(0., 1.)
+    }
+}
+
+// equal power curves, the sum of both songs' power stays constant so the mix doesn't dip
+// in the middle like a linear fade does
+fn fade_in_gain(progress: f32) -> f32 {
+    (progress * FRAC_PI_2).sin()
+}
+
+fn fade_out_gain(progress: f32) -> f32 {
+    (progress * FRAC_PI_2).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LENGTH: Duration = Duration::from_secs(4);
+
+    fn secs(secs: f32) -> Duration {
+        Duration::from_secs_f32(secs)
+    }
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{a} != {b}");
+    }
+
+    #[test]
+    fn fade_in_rises_from_silence_to_full() {
+        let fade = Fade::In { length: LENGTH };
+        assert_close(fade.gain(Duration::ZERO), 0.);
+        assert_close(fade.gain(LENGTH), 1.);
+    }
+
+    #[test]
+    fn fade_out_drops_from_full_to_silence() {
+        let fade = Fade::Out {
+            from: secs(10.),
+            length: LENGTH,
+        };
+        assert_close(fade.gain(secs(10.)), 1.);
+        assert_close(fade.gain(secs(14.)), 0.);
+        // untouched before the fade starts
+        assert_close(fade.gain(secs(5.)), 1.);
+    }
+
+    #[test]
+    fn fades_keep_equal_power() {
+        let fade_in = Fade::In { length: LENGTH };
+        let fade_out = Fade::Out {
+            from: Duration::ZERO,
+            length: LENGTH,
+        };
+        for step in 0..=40 {
+            let elapsed = LENGTH * step / 40;
+            let power = fade_in.gain(elapsed).powi(2) + fade_out.gain(elapsed).powi(2);
+            assert_close(power, 1.);
+        }
+    }
+
+    #[test]
+    fn fades_hold_past_the_end() {
+        let fade_in = Fade::In { length: LENGTH };
+        assert_close(fade_in.gain(secs(60.)), 1.);
+        assert!(!fade_in.finished(secs(60.)));
+
+        let fade_out = Fade::Out {
+            from: secs(10.),
+            length: LENGTH,
+        };
+        assert!(!fade_out.finished(secs(13.9)));
+        assert!(fade_out.finished(secs(14.)));
+        assert_close(fade_out.gain(secs(60.)), 0.);
+    }
+
+    #[test]
+    fn zero_length_fades_are_instant() {
+        assert_close(
+            Fade::In {
+                length: Duration::ZERO,
+            }
+            .gain(Duration::ZERO),
+            1.,
+        );
+        let fade_out = Fade::Out {
+            from: secs(1.),
+            length: Duration::ZERO,
+        };
+        assert!(fade_out.finished(secs(1.)));
+    }
+}
@@ -0,0 +1,190 @@
+use std::{fs::File, path::Path, time::Duration};
+
+use rodio::Source;
+use symphonia::{
+    core::{
+        audio::{SampleBuffer, SignalSpec},
+        codecs::{Decoder as CodecDecoder, DecoderOptions},
+        errors::{Error, Result},
+        formats::{FormatOptions, FormatReader, SeekMode, SeekTo},
+        io::MediaSourceStream,
+        meta::MetadataOptions,
+        probe::Hint,
+        units::{self, Time, TimeBase},
+    },
+    default::{get_codecs, get_probe},
+};
+
+// Decode errors are not fatal, the packet is skipped and the next one tried.
+// More than 3 consecutive bad packets ends the track.
+const MAX_DECODE_ERRORS: usize = 3;
+
+/// Audio decoder that, unlike `rodio::Decoder`, can jump to any position in the track.
+pub struct Decoder {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn CodecDecoder>,
+    track_id: u32,
+    time_base: Option<TimeBase>,
+    // timestamp of the packet currently in `buffer`
+    ts: u64,
+    spec: SignalSpec,
+    buffer: SampleBuffer<f32>,
+    offset: usize,
+    // samples still to be dropped after an accurate seek landed before the requested position
+    skip: usize,
+}
+
+impl Decoder {
+    pub fn new(path: &Path) -> Result<Self> {
+        let mut hint = Hint::new();
+        if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+            hint.with_extension(ext);
+        }
+
+        let mss = MediaSourceStream::new(Box::new(File::open(path)?), Default::default());
+        let probed = get_probe().format(
+            &hint,
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )?;
+
+        let track = probed
+            .format
+            .default_track()
+            .ok_or(Error::Unsupported("no playable track"))?;
+        let track_id = track.id;
+        let time_base = track.codec_params.time_base;
+        let decoder = get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+        let mut decoder = Self {
+            format: probed.format,
+            decoder,
+            track_id,
+            time_base,
+            ts: 0,
+            spec: SignalSpec::new(0, Default::default()),
+            buffer: SampleBuffer::new(0, SignalSpec::new(0, Default::default())),
+            offset: 0,
+            skip: 0,
+        };
+
+        // decode the first packet so channels and sample rate are known up front
+        if !decoder.decode_next() {
+            return Err(Error::DecodeError("no audio data"));
+        }
+
+        Ok(decoder)
+    }
+
+    /// Jump to `pos` from the start of the track.
+    pub fn seek(&mut self, pos: Duration) -> Result<()> {
+        let seeked = self.format.seek(
+            SeekMode::Accurate,
+            SeekTo::Time {
+                time: Time::from(pos.as_secs_f64()),
+                track_id: Some(self.track_id),
+            },
+        )?;
+        self.decoder.reset();
+
+        // the seek may land on the packet before the requested position, drop the difference
+        let frames = match self.time_base {
+            Some(tb) => {
+                let time = tb.calc_time(seeked.required_ts.saturating_sub(seeked.actual_ts));
+                ((time.seconds as f64 + time.frac) * self.spec.rate as f64) as usize
+            }
+            None => 0,
+        };
+        self.skip = frames * self.spec.channels.count();
+        self.ts = seeked.required_ts;
+        self.buffer.clear();
+        self.offset = 0;
+
+        Ok(())
+    }
+
+    /// Position of the next sample to be played, from the start of the track.
+    pub fn elapsed(&self) -> Duration {
+        let rate = self.spec.rate.max(1) as f64;
+        let start = match self.time_base {
+            Some(tb) => {
+                let time = tb.calc_time(self.ts);
+                Duration::from_secs(time.seconds) + Duration::from_secs_f64(time.frac)
+            }
+            // without a time base packet timestamps count frames
+            None => Duration::from_secs_f64(self.ts as f64 / rate),
+        };
+        let frames = self.offset / self.spec.channels.count().max(1);
+
+        start + Duration::from_secs_f64(frames as f64 / rate)
+    }
+
+    // refill the sample buffer, returns false at the end of the track
+    fn decode_next(&mut self) -> bool {
+        let mut decode_errors = 0;
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(_) => return false,
+            };
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            match self.decoder.decode(&packet) {
+                Ok(decoded) => {
+                    self.ts = packet.ts();
+                    self.spec = *decoded.spec();
+                    self.buffer =
+                        SampleBuffer::new(decoded.capacity() as units::Duration, self.spec);
+                    self.buffer.copy_interleaved_ref(decoded);
+                    self.offset = 0;
+                    return true;
+                }
+                Err(Error::DecodeError(_)) if decode_errors < MAX_DECODE_ERRORS => {
+                    decode_errors += 1;
+                }
+                Err(_) => return false,
+            }
+        }
+    }
+}
+
+impl Iterator for Decoder {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        loop {
+            if self.offset >= self.buffer.len() && !self.decode_next() {
+                return None;
+            }
+
+            let sample = self.buffer.samples()[self.offset];
+            self.offset += 1;
+
+            if self.skip == 0 {
+                return Some(sample);
+            }
+            self.skip -= 1;
+        }
+    }
+}
+
+impl Source for Decoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.buffer.len() - self.offset)
+    }
+
+    fn channels(&self) -> u16 {
+        self.spec.channels.count() as u16
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.spec.rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
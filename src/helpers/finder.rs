@@ -0,0 +1,124 @@
+use std::path::PathBuf;
+
+use super::{gen_funcs, library::Song};
+
+// more results than fit on any screen aren't worth ranking
+const MAX_RESULTS: usize = 200;
+
+// bonuses and penalties for how a query lines up with a candidate
+const MATCH: i64 = 1;
+const CONSECUTIVE: i64 = 5;
+const WORD_START: i64 = 8;
+const MAX_GAP_PENALTY: i64 = 10;
+
+/// Fuzzy finder over every song in the library, matching "artist - title" and the path.
+pub struct Finder {
+    query: String,
+    // indices into the songs, best match first
+    results: Vec<usize>,
+    selected: usize,
+}
+
+impl Finder {
+    pub fn new(songs: &[(PathBuf, Song)]) -> Self {
+        let mut finder = Self {
+            query: String::new(),
+            results: Vec::new(),
+            selected: 0,
+        };
+        finder.update(songs);
+        finder
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn push(&mut self, c: char, songs: &[(PathBuf, Song)]) {
+        self.query.push(c);
+        self.update(songs);
+    }
+
+    pub fn pop(&mut self, songs: &[(PathBuf, Song)]) {
+        self.query.pop();
+        self.update(songs);
+    }
+
+    pub fn results(&self) -> &[usize] {
+        &self.results
+    }
+
+    /// Position of the highlighted result within `results`.
+    pub fn current(&self) -> Option<usize> {
+        (!self.results.is_empty()).then_some(self.selected)
+    }
+
+    /// Index of the highlighted song.
+    pub fn selected(&self) -> Option<usize> {
+        self.results.get(self.selected).copied()
+    }
+
+    pub fn next(&mut self) {
+        if !self.results.is_empty() {
+            self.selected = (self.selected + 1) % self.results.len();
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if !self.results.is_empty() {
+            self.selected = (self.selected + self.results.len() - 1) % self.results.len();
+        }
+    }
+
+    fn update(&mut self, songs: &[(PathBuf, Song)]) {
+        let mut ranked: Vec<(i64, usize)> = songs
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (path, song))| {
+                let display = gen_funcs::song_display(path, Some(song));
+                let by_name = score(&self.query, &display);
+                let by_path = score(&self.query, &path.to_string_lossy());
+                Some((by_name.max(by_path)?, i))
+            })
+            .collect();
+
+        // best score first, ties keep library order
+        ranked.sort_by_key(|&(score, i)| (-score, i));
+        self.results = ranked
+            .into_iter()
+            .take(MAX_RESULTS)
+            .map(|(_, i)| i)
+            .collect();
+        self.selected = 0;
+    }
+}
+
+/// How well `candidate` matches `query` when the query's characters appear in it in order,
+/// ignoring case and spaces. Runs of consecutive characters and characters at the start of words
+/// score higher, long gaps between them lower. None if it doesn't match at all.
+pub fn score(query: &str, candidate: &str) -> Option<i64> {
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut last = None;
+
+    for c in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let i = (pos..candidate.len()).find(|&i| candidate[i] == c)?;
+
+        score += MATCH;
+        if i > 0 && last == Some(i - 1) {
+            score += CONSECUTIVE;
+        }
+        if i == 0 || !candidate[i - 1].is_alphanumeric() {
+            score += WORD_START;
+        }
+        if last.is_some() {
+            score -= ((i - pos) as i64).min(MAX_GAP_PENALTY);
+        }
+
+        last = Some(i);
+        pos = i + 1;
+    }
+
+    Some(score)
+}
@@ -1,48 +1,61 @@
 use std::{
+    cmp::Ordering,
     collections::HashSet,
     env,
     ffi::OsStr,
+    fs,
+    iter::Peekable,
     path::{Path, PathBuf},
+    str::Chars,
 };
 
 use glob::glob;
-use lofty::{Accessor, AudioFile, Probe, TaggedFileExt};
+
+use super::{
+    library::{self, Song},
+    playlist,
+};
+
+const AUDIO_EXTENSIONS: [&str; 7] = ["mp3", "mp4", "m4a", "wav", "flac", "ogg", "aac"];
 
 // converts queue items to what's displayed for user
-pub fn audio_display(path: &PathBuf) -> String {
-    let path = Path::new(&path);
-    let tagged_file = Probe::open(path)
-        .expect("ERROR: Bad path provided!")
-        .read()
-        .expect("ERROR: Failed to read file!");
-
-    // If the file contains a tag
-    if tagged_file.contains_tag() {
-        let ptag = tagged_file.primary_tag().unwrap();
-        let artist = ptag.artist();
-
-        // if filename
-        if let Some(i) = tagged_file.primary_tag().unwrap().title() {
-            // if artist data
-            if let Some(j) = artist {
-                format!("{artist} - {title}", artist = j, title = i)
-            } else {
-                i.into()
-            }
-        } else {
-            path.file_name().unwrap().to_str().unwrap().to_string()
-        }
-    } else {
-        path.file_name().unwrap().to_str().unwrap().to_string()
+pub fn audio_display(path: &Path) -> String {
+    song_display(path, library::song(path).as_ref())
+}
+
+// "artist - title" from the tags, the file name if there's no title
+pub fn song_display(path: &Path, song: Option<&Song>) -> String {
+    let title = song.and_then(|song| song.title.as_ref());
+    let artist = song.and_then(|song| song.artist.as_ref());
+
+    match (title, artist) {
+        (Some(title), Some(artist)) => format!("{artist} - {title}"),
+        (Some(title), None) => title.clone(),
+        _ => path.file_name().unwrap().to_str().unwrap().to_string(),
     }
 }
 
+// true if both songs are tagged with the same album, or untagged and in the same folder
+pub fn same_album(a: &Path, b: &Path) -> bool {
+    match (album(a), album(b)) {
+        (Some(a), Some(b)) => a == b,
+        (None, None) => a.parent() == b.parent(),
+        _ => false,
+    }
+}
+
+// album artist and album title from the file's tags
+fn album(path: &Path) -> Option<(Option<String>, String)> {
+    let song = library::song(path)?;
+    let title = song.album?;
+    let artist = song.album_artist.or(song.artist);
+
+    Some((artist, title))
+}
+
 pub fn scan_and_filter_directory() -> Vec<String> {
     let mut items = Vec::new();
-    let valid_extensions: HashSet<&str> = ["mp3", "mp4", "m4a", "wav", "flac", "ogg", "aac"]
-        .iter()
-        .cloned()
-        .collect();
+    let valid_extensions: HashSet<&str> = AUDIO_EXTENSIONS.iter().cloned().collect();
 
     let current_dir = env::current_dir().expect("Failed to get current directory");
 
@@ -57,7 +70,7 @@ pub fn scan_and_filter_directory() -> Vec<String> {
                 }
             }
         } else if let Some(ext) = path.extension().and_then(OsStr::to_str) {
-            if valid_extensions.contains(ext) {
+            if valid_extensions.contains(ext) || playlist::is_playlist(&path) {
                 items.push(entry.to_str().unwrap().to_owned());
             }
         }
@@ -65,31 +78,209 @@ pub fn scan_and_filter_directory() -> Vec<String> {
     items
 }
 
-// scans folder for valid files, returns matches
-// need to set current dir
-pub fn bulk_add(selected: &PathBuf) -> Vec<PathBuf> {
+// every audio file below `dir`, in no particular order. `max_depth` 1 only looks in `dir`
+// itself, 2 in its subfolders too and so on. hidden folders are skipped
+pub fn scan_recursive(dir: &Path, max_depth: usize) -> Vec<PathBuf> {
     let mut items = Vec::new();
-    env::set_current_dir(selected).unwrap();
-
-    for item in glob::glob("./*")
-        .expect("Failed to read glob pattern")
-        .flatten()
-    {
-        let current_dir = env::current_dir().unwrap();
-        let join = Path::join(&current_dir, Path::new(&item));
-        let ext = Path::new(&item).extension().and_then(OsStr::to_str);
-        if ext.is_some()
-            && (item.extension().unwrap() == "mp3"
-                || item.extension().unwrap() == "mp4"
-                || item.extension().unwrap() == "m4a"
-                || item.extension().unwrap() == "wav"
-                || item.extension().unwrap() == "flac"
-                || item.extension().unwrap() == "ogg"
-                || item.extension().unwrap() == "aac")
-        {
-            items.push(join);
+    let mut dirs = vec![(dir.to_path_buf(), 1)];
+
+    while let Some((dir, depth)) = dirs.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+
+        for path in entries.flatten().map(|entry| entry.path()) {
+            let hidden = path
+                .file_name()
+                .and_then(OsStr::to_str)
+                .is_some_and(|name| name.starts_with('.'));
+
+            if path.is_dir() {
+                if !hidden && depth < max_depth {
+                    dirs.push((path, depth + 1));
+                }
+            } else if is_audio_file(&path) {
+                items.push(path);
+            }
         }
     }
-    env::set_current_dir("../").unwrap();
     items
 }
+
+// whether the extension is one of the formats that can be played
+pub fn is_audio_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext))
+}
+
+// audio files in `selected` and up to `max_depth` levels of subfolders, folder by folder. only
+// paths are looked at, so this stays quick for big folders; `track_order` puts them in play order
+pub fn bulk_add(selected: &Path, max_depth: usize) -> Vec<PathBuf> {
+    let mut items = scan_recursive(selected, max_depth);
+    items.sort_by(|a, b| {
+        folder_cmp(a, b).then_with(|| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()))
+    });
+    items
+}
+
+// songs from `bulk_add` in play order: folder by folder, tagged songs by disc and track number,
+// the rest by file name. reads the tags of every song
+pub fn track_order(songs: &mut [PathBuf]) {
+    let mut items: Vec<(PathBuf, Option<(u32, u32)>)> = songs
+        .iter()
+        .map(|path| {
+            let song = library::song(path);
+            let number = song.and_then(|song| Some((song.disc.unwrap_or(1), song.track?)));
+            (path.clone(), number)
+        })
+        .collect();
+
+    sort_numbered(&mut items);
+
+    for (song, (path, _)) in songs.iter_mut().zip(items) {
+        *song = path;
+    }
+}
+
+// the sort behind `track_order`, on songs paired with their disc and track number
+fn sort_numbered(items: &mut [(PathBuf, Option<(u32, u32)>)]) {
+    items.sort_by(|(a, a_number), (b, b_number)| {
+        folder_cmp(a, b)
+            .then_with(|| match (a_number, b_number) {
+                (Some(a_number), Some(b_number)) => a_number.cmp(b_number),
+                // numbered songs go before the ones without
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            })
+            .then_with(|| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()))
+    });
+}
+
+fn folder_cmp(a: &Path, b: &Path) -> Ordering {
+    let a_folder = a.parent().unwrap_or(a).to_string_lossy();
+    let b_folder = b.parent().unwrap_or(b).to_string_lossy();
+    natural_cmp(&a_folder, &b_folder)
+}
+
+// compares strings the way people count, "track 2" before "track 10". runs of digits compare
+// by value, everything else case insensitively
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x = take_number(&mut a);
+                let y = take_number(&mut b);
+                // leading zeros don't change the value, compare lengths once they're gone
+                let x = x.trim_start_matches('0');
+                let y = y.trim_start_matches('0');
+                let ordering = x.len().cmp(&y.len()).then_with(|| x.cmp(y));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+fn take_number(chars: &mut Peekable<Chars>) -> String {
+    let mut number = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+        number.push(c);
+    }
+    number
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_compare_by_value() {
+        assert_eq!(natural_cmp("2", "10"), Ordering::Less);
+        assert_eq!(natural_cmp("track 9.mp3", "track 10.mp3"), Ordering::Less);
+        assert_eq!(natural_cmp("disc 2/1", "disc 10/1"), Ordering::Less);
+        assert_eq!(natural_cmp("10", "9"), Ordering::Greater);
+    }
+
+    #[test]
+    fn leading_zeros_dont_count() {
+        assert_eq!(natural_cmp("01", "1"), Ordering::Equal);
+        assert_eq!(natural_cmp("002", "10"), Ordering::Less);
+        assert_eq!(natural_cmp("track 010", "track 9"), Ordering::Greater);
+        assert_eq!(natural_cmp("0", "00"), Ordering::Equal);
+    }
+
+    #[test]
+    fn letters_compare_ignoring_case() {
+        assert_eq!(natural_cmp("abc", "ABC"), Ordering::Equal);
+        assert_eq!(natural_cmp("apple", "Banana"), Ordering::Less);
+        assert_eq!(natural_cmp("song", "song 2"), Ordering::Less);
+    }
+
+    #[test]
+    fn numbered_songs_sort_by_disc_then_track() {
+        let mut items = vec![
+            (PathBuf::from("/b/x.mp3"), None),
+            (PathBuf::from("/a/intro.mp3"), Some((2, 1))),
+            (PathBuf::from("/a/zebra.mp3"), Some((1, 10))),
+            (PathBuf::from("/a/bonus.mp3"), None),
+            (PathBuf::from("/a/mango.mp3"), Some((1, 2))),
+            (PathBuf::from("/a/track 10.mp3"), None),
+            (PathBuf::from("/a/track 9.mp3"), None),
+        ];
+        sort_numbered(&mut items);
+
+        let order: Vec<_> = items
+            .iter()
+            .map(|(path, _)| path.to_str().unwrap())
+            .collect();
+        assert_eq!(
+            order,
+            [
+                "/a/mango.mp3",
+                "/a/zebra.mp3",
+                "/a/intro.mp3",
+                "/a/bonus.mp3",
+                "/a/track 9.mp3",
+                "/a/track 10.mp3",
+                "/b/x.mp3",
+            ]
+        );
+    }
+
+    #[test]
+    fn folders_sort_naturally() {
+        // untagged files that don't exist, so only the names count
+        let mut songs = vec![
+            PathBuf::from("/music/cd 10/a.mp3"),
+            PathBuf::from("/music/cd 2/b.mp3"),
+            PathBuf::from("/music/cd 2/a.mp3"),
+        ];
+        track_order(&mut songs);
+
+        assert_eq!(
+            songs,
+            [
+                PathBuf::from("/music/cd 2/a.mp3"),
+                PathBuf::from("/music/cd 2/b.mp3"),
+                PathBuf::from("/music/cd 10/a.mp3"),
+            ]
+        );
+    }
+}
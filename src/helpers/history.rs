@@ -0,0 +1,159 @@
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use tui::widgets::ListState;
+
+use super::gen_funcs;
+use crate::constants::{SECONDS_PER_DAY, SECONDS_PER_HOUR, SECONDS_PER_MINUTE};
+
+// older songs are forgotten
+const HISTORY_LIMIT: usize = 500;
+
+/// Songs played this session and when they started, listed newest first.
+pub struct History {
+    // oldest first
+    entries: VecDeque<(PathBuf, SystemTime)>,
+    // index into the list as shown, newest first
+    selected: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self {
+            entries: VecDeque::new(),
+            selected: 0,
+        }
+    }
+
+    /// Note that `path` started playing, unless it's the song last noted.
+    pub fn record(&mut self, path: &Path) {
+        if self.last().is_some_and(|last| last == path) {
+            return;
+        }
+
+        self.entries
+            .push_back((path.to_path_buf(), SystemTime::now()));
+        if self.entries.len() > HISTORY_LIMIT {
+            self.entries.pop_front();
+        }
+        // the selection stays on the same song
+        self.selected = (self.selected + 1).min(self.entries.len() - 1);
+    }
+
+    /// The song that started last.
+    pub fn last(&self) -> Option<&PathBuf> {
+        self.entries.back().map(|(path, _)| path)
+    }
+
+    /// Forget the song that started last, returning it.
+    pub fn pop(&mut self) -> Option<PathBuf> {
+        let (path, _) = self.entries.pop_back()?;
+        self.selected = self.selected.saturating_sub(1);
+        Some(path)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// "artist - title" and how long ago it started, newest first.
+    pub fn items(&self) -> Vec<String> {
+        let now = SystemTime::now();
+        self.entries
+            .iter()
+            .rev()
+            .map(|(path, started)| {
+                let ago = now.duration_since(*started).unwrap_or_default();
+                format!("{}  ({})", gen_funcs::audio_display(path), format_ago(ago))
+            })
+            .collect()
+    }
+
+    pub fn selected(&self) -> Option<&PathBuf> {
+        let i = self.entries.len().checked_sub(self.selected + 1)?;
+        self.entries.get(i).map(|(path, _)| path)
+    }
+
+    pub fn state(&self) -> ListState {
+        let mut state = ListState::default();
+        if !self.entries.is_empty() {
+            state.select(Some(self.selected));
+        }
+        state
+    }
+
+    pub fn next(&mut self) {
+        if !self.entries.is_empty() {
+            self.selected = (self.selected + 1) % self.entries.len();
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if !self.entries.is_empty() {
+            let len = self.entries.len();
+            self.selected = (self.selected + len - 1) % len;
+        }
+    }
+}
+
+fn format_ago(ago: Duration) -> String {
+    let secs = ago.as_secs() as u32;
+    if secs < SECONDS_PER_MINUTE {
+        "just now".to_string()
+    } else if secs < SECONDS_PER_HOUR {
+        format!("{} min ago", secs / SECONDS_PER_MINUTE)
+    } else if secs < SECONDS_PER_DAY {
+        format!("{} h ago", secs / SECONDS_PER_HOUR)
+    } else {
+        format!("{} d ago", secs / SECONDS_PER_DAY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(paths: &[&str]) -> History {
+        let mut history = History::new();
+        for path in paths {
+            history.record(Path::new(path));
+        }
+        history
+    }
+
+    #[test]
+    fn selection_stays_on_the_newest_song() {
+        let mut history = history(&["/a/1"]);
+        assert_eq!(history.selected().unwrap(), Path::new("/a/1"));
+
+        history.record(Path::new("/a/2"));
+        assert_eq!(history.selected().unwrap(), Path::new("/a/1"));
+    }
+
+    #[test]
+    fn selection_stays_on_an_older_song() {
+        let mut history = history(&["/a/1", "/a/2", "/a/3"]);
+        history.previous();
+        assert_eq!(history.selected().unwrap(), Path::new("/a/2"));
+
+        history.record(Path::new("/a/4"));
+        assert_eq!(history.selected().unwrap(), Path::new("/a/2"));
+        // the same song again isn't recorded twice
+        history.record(Path::new("/a/4"));
+        assert_eq!(history.len(), 4);
+        assert_eq!(history.selected().unwrap(), Path::new("/a/2"));
+    }
+}
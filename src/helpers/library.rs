@@ -0,0 +1,254 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{LazyLock, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use lofty::{Accessor, AudioFile, ItemKey, Probe, TaggedFileExt};
+use serde::{Deserialize, Serialize};
+
+// bumped whenever `Song` changes so caches written by older versions are rebuilt
+const CACHE_VERSION: u32 = 3;
+
+static CACHE: LazyLock<Mutex<Cache>> = LazyLock::new(|| Mutex::new(Cache::load()));
+
+// titles and lengths given by playlists, never written to the cache
+static PLAYLIST_INFO: LazyLock<Mutex<HashMap<PathBuf, PlaylistInfo>>> =
+    LazyLock::new(Default::default);
+
+type PlaylistInfo = (Option<String>, Option<Duration>);
+
+/// What the tags and headers of a song say about it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Song {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub track: Option<u32>,
+    pub disc: Option<u32>,
+    pub genre: Option<String>,
+    pub year: Option<u32>,
+    pub duration: Duration,
+    pub format: String,
+}
+
+impl Song {
+    fn read(path: &Path) -> Option<Self> {
+        let tagged_file = Probe::open(path).ok()?.read().ok()?;
+        let tag = tagged_file.primary_tag().or(tagged_file.first_tag());
+
+        Some(Self {
+            title: tag.and_then(|t| t.title()).map(|t| t.into_owned()),
+            artist: tag.and_then(|t| t.artist()).map(|a| a.into_owned()),
+            album: tag.and_then(|t| t.album()).map(|a| a.into_owned()),
+            album_artist: tag
+                .and_then(|t| t.get_string(&ItemKey::AlbumArtist))
+                .map(String::from),
+            track: tag.and_then(|t| t.track()),
+            disc: tag.and_then(|t| t.disk()),
+            genre: tag.and_then(|t| t.genre()).map(|g| g.into_owned()),
+            year: tag.and_then(|t| t.year()),
+            duration: tagged_file.properties().duration(),
+            format: format!("{:?}", tagged_file.file_type()),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    modified: SystemTime,
+    size: u64,
+    song: Song,
+    // compared against the file once per run, trusted after that
+    #[serde(skip)]
+    checked: bool,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Cache {
+    version: u32,
+    entries: HashMap<PathBuf, Entry>,
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl Cache {
+    fn load() -> Self {
+        let cache = fs::read(cache_path())
+            .ok()
+            .and_then(|content| bincode::deserialize::<Cache>(&content).ok());
+
+        match cache {
+            Some(cache) if cache.version == CACHE_VERSION => cache,
+            _ => Self {
+                version: CACHE_VERSION,
+                ..Default::default()
+            },
+        }
+    }
+
+    // the cached song if it was checked against the file this run
+    fn checked(&self, path: &Path) -> Option<Song> {
+        let entry = self.entries.get(path).filter(|entry| entry.checked)?;
+        Some(entry.song.clone())
+    }
+
+    // the cached song if the file hasn't changed since it was cached
+    fn unchanged(&mut self, path: &Path, modified: SystemTime, size: u64) -> Option<Song> {
+        let entry = self
+            .entries
+            .get_mut(path)
+            .filter(|entry| entry.modified == modified && entry.size == size)?;
+        entry.checked = true;
+        Some(entry.song.clone())
+    }
+
+    fn insert(&mut self, path: &Path, modified: SystemTime, size: u64, song: Song) {
+        self.entries.insert(
+            path.to_path_buf(),
+            Entry {
+                modified,
+                size,
+                song,
+                checked: true,
+            },
+        );
+        self.dirty = true;
+    }
+}
+
+fn cache_path() -> PathBuf {
+    home::home_dir()
+        .unwrap()
+        .as_path()
+        .join(".config/kronos/library.bin")
+}
+
+/// Tags and length of the song at `path`, read from the file only the first time it's seen or
+/// after it changed. None if it isn't a readable audio file. Songs without a title or length
+/// of their own get the ones a playlist gave them.
+pub fn song(path: &Path) -> Option<Song> {
+    let mut song = tagged(path)?;
+
+    if let Some((title, duration)) = PLAYLIST_INFO.lock().unwrap().get(path) {
+        // a playlist title often has the artist in it already
+        if song.title.is_none() && song.artist.is_none() {
+            song.title = title.clone();
+        }
+        if song.duration.is_zero() {
+            song.duration = duration.unwrap_or_default();
+        }
+    }
+
+    Some(song)
+}
+
+/// Remember the title and length a playlist lists the song at `path` with.
+pub fn add_playlist_info(path: &Path, title: Option<String>, duration: Option<Duration>) {
+    if title.is_some() || duration.is_some() {
+        PLAYLIST_INFO
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), (title, duration));
+    }
+}
+
+fn tagged(path: &Path) -> Option<Song> {
+    if let Some(song) = CACHE.lock().unwrap().checked(path) {
+        return Some(song);
+    }
+
+    // a song changed on disk since it was cached is read again
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok()?;
+    let size = metadata.len();
+    if let Some(song) = CACHE.lock().unwrap().unchanged(path, modified, size) {
+        return Some(song);
+    }
+
+    // the file is read without holding the cache, so other lookups don't wait on it
+    let song = Song::read(path)?;
+    CACHE
+        .lock()
+        .unwrap()
+        .insert(path, modified, size, song.clone());
+    Some(song)
+}
+
+/// Write songs read this run to disk so the next run doesn't have to read them again.
+pub fn save() -> Result<(), String> {
+    let mut cache = CACHE.lock().unwrap();
+
+    // songs that were deleted since they were cached
+    let cached = cache.entries.len();
+    cache
+        .entries
+        .retain(|path, entry| entry.checked || path.exists());
+    if cache.entries.len() != cached {
+        cache.dirty = true;
+    }
+
+    if !cache.dirty {
+        return Ok(());
+    }
+
+    let path = cache_path();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+
+    bincode::serialize(&*cache)
+        .map_err(|e| e.to_string())
+        .and_then(|serialized| fs::write(path, serialized).map_err(|e| e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a wav file without tags, `seconds` long
+    fn untagged_song(name: &str, seconds: u32) -> PathBuf {
+        let rate: u32 = 8000;
+        let data = rate * seconds;
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        // pcm, mono, 8000 samples of 8 bits a second
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&rate.to_le_bytes());
+        wav.extend_from_slice(&rate.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&8u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data.to_le_bytes());
+        wav.resize(wav.len() + data as usize, 128);
+
+        let path = std::env::temp_dir().join(format!("kronos-{}-{name}.wav", std::process::id()));
+        fs::write(&path, wav).unwrap();
+        path
+    }
+
+    #[test]
+    fn playlist_info_fills_in_missing_tags() {
+        let path = untagged_song("playlist-info", 1);
+        assert_eq!(song(&path).unwrap().title, None);
+
+        add_playlist_info(
+            &path,
+            Some("Artist - Title".into()),
+            Some(Duration::from_secs(60)),
+        );
+        let song = song(&path).unwrap();
+        assert_eq!(song.title.as_deref(), Some("Artist - Title"));
+        // the file knows its own length better
+        assert_eq!(song.duration, Duration::from_secs(1));
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,346 @@
+use std::{
+    fmt,
+    path::PathBuf,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+};
+
+use tui::widgets::ListState;
+
+use super::{
+    gen_funcs,
+    library::{self, Song},
+};
+
+// columns of the library tab, left to right
+pub const GROUPS: usize = 0;
+pub const ALBUMS: usize = 1;
+pub const TRACKS: usize = 2;
+
+/// What songs are grouped by in the first column of the library.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Grouping {
+    #[default]
+    Artist,
+    Genre,
+    Year,
+}
+
+impl Grouping {
+    pub fn next(&self) -> Self {
+        match self {
+            Grouping::Artist => Grouping::Genre,
+            Grouping::Genre => Grouping::Year,
+            Grouping::Year => Grouping::Artist,
+        }
+    }
+
+    fn key(&self, song: &Song) -> String {
+        match self {
+            Grouping::Artist => song
+                .album_artist
+                .clone()
+                .or_else(|| song.artist.clone())
+                .unwrap_or_else(|| "Unknown Artist".into()),
+            Grouping::Genre => song.genre.clone().unwrap_or_else(|| "Unknown Genre".into()),
+            Grouping::Year => song
+                .year
+                .map(|year| year.to_string())
+                .unwrap_or_else(|| "Unknown Year".into()),
+        }
+    }
+}
+
+impl fmt::Display for Grouping {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Grouping::Artist => write!(f, "Artists"),
+            Grouping::Genre => write!(f, "Genres"),
+            Grouping::Year => write!(f, "Years"),
+        }
+    }
+}
+
+fn album_key(song: &Song) -> String {
+    song.album.clone().unwrap_or_else(|| "Unknown Album".into())
+}
+
+// groups and albums only differing in case are the same, they're sorted that way too
+fn same_key(a: &str, b: &str) -> bool {
+    a.to_lowercase() == b.to_lowercase()
+}
+
+/// Songs below the library folder, browsed by artist (or genre, or year), then album, then track.
+pub struct LibraryBrowser {
+    songs: Vec<(PathBuf, Song)>,
+    grouping: Grouping,
+    groups: Vec<String>,
+    albums: Vec<String>,
+    // indices into `songs`
+    tracks: Vec<usize>,
+    selected: [usize; 3],
+    focus: usize,
+    scan: Option<Receiver<(PathBuf, Song)>>,
+}
+
+impl Default for LibraryBrowser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LibraryBrowser {
+    pub fn new() -> Self {
+        Self {
+            songs: Vec::new(),
+            grouping: Grouping::default(),
+            groups: Vec::new(),
+            albums: Vec::new(),
+            tracks: Vec::new(),
+            selected: [0; 3],
+            focus: GROUPS,
+            scan: None,
+        }
+    }
+
+    /// Read every song below `root` in the background, they show up as `update` picks them up.
+    pub fn scan(&mut self, root: PathBuf) {
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            for path in gen_funcs::scan_recursive(&root, usize::MAX) {
+                if let Some(song) = library::song(&path) {
+                    // the browser is gone, nobody is listening
+                    if sender.send((path, song)).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        self.scan = Some(receiver);
+    }
+
+    /// Take in the songs scanned since the last call.
+    pub fn update(&mut self) {
+        let Some(receiver) = &self.scan else {
+            return;
+        };
+
+        let mut received = false;
+        loop {
+            match receiver.try_recv() {
+                Ok(song) => {
+                    self.songs.push(song);
+                    received = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.scan = None;
+                    break;
+                }
+            }
+        }
+
+        if received {
+            self.rebuild();
+        }
+    }
+
+    pub fn scanning(&self) -> bool {
+        self.scan.is_some()
+    }
+
+    pub fn grouping(&self) -> Grouping {
+        self.grouping
+    }
+
+    pub fn cycle_grouping(&mut self) {
+        self.grouping = self.grouping.next();
+        self.groups.clear();
+        self.albums.clear();
+        self.tracks.clear();
+        self.selected = [0; 3];
+        self.focus = GROUPS;
+        self.rebuild();
+    }
+
+    /// Every song scanned so far.
+    pub fn songs(&self) -> &[(PathBuf, Song)] {
+        &self.songs
+    }
+
+    pub fn groups(&self) -> &[String] {
+        &self.groups
+    }
+
+    pub fn albums(&self) -> &[String] {
+        &self.albums
+    }
+
+    pub fn tracks(&self) -> impl Iterator<Item = &(PathBuf, Song)> {
+        self.tracks.iter().map(|&i| &self.songs[i])
+    }
+
+    /// Which column has focus, one of `GROUPS`, `ALBUMS` or `TRACKS`.
+    pub fn focus(&self) -> usize {
+        self.focus
+    }
+
+    pub fn state(&self, column: usize) -> ListState {
+        let len = [self.groups.len(), self.albums.len(), self.tracks.len()][column];
+        let mut state = ListState::default();
+        if len > 0 {
+            state.select(Some(self.selected[column]));
+        }
+        state
+    }
+
+    pub fn next(&mut self) {
+        let len = self.column_len();
+        if len > 0 {
+            self.select((self.selected[self.focus] + 1) % len);
+        }
+    }
+
+    pub fn previous(&mut self) {
+        let len = self.column_len();
+        if len > 0 {
+            self.select((self.selected[self.focus] + len - 1) % len);
+        }
+    }
+
+    /// Move into the selected artist or album, returns the song to play when a track is selected.
+    pub fn descend(&mut self) -> Option<PathBuf> {
+        if self.focus == TRACKS {
+            let &i = self.tracks.get(self.selected[TRACKS])?;
+            Some(self.songs[i].0.clone())
+        } else {
+            if self.column_len() > 0 {
+                self.focus += 1;
+            }
+            None
+        }
+    }
+
+    pub fn ascend(&mut self) {
+        self.focus = self.focus.saturating_sub(1);
+    }
+
+    /// Every song of the selected artist, album or track, in track number order.
+    pub fn selection(&self) -> Vec<PathBuf> {
+        let indices = match self.focus {
+            GROUPS => match self.groups.get(self.selected[GROUPS]) {
+                Some(group) => self.songs_in(group, None),
+                None => Vec::new(),
+            },
+            ALBUMS => self.tracks.clone(),
+            _ => self
+                .tracks
+                .get(self.selected[TRACKS])
+                .copied()
+                .into_iter()
+                .collect(),
+        };
+
+        indices
+            .into_iter()
+            .map(|i| self.songs[i].0.clone())
+            .collect()
+    }
+
+    fn column_len(&self) -> usize {
+        [self.groups.len(), self.albums.len(), self.tracks.len()][self.focus]
+    }
+
+    fn select(&mut self, i: usize) {
+        self.selected[self.focus] = i;
+
+        // columns to the right follow the new selection
+        if self.focus == GROUPS {
+            self.selected[ALBUMS] = 0;
+            self.refresh_albums();
+        }
+        if self.focus <= ALBUMS {
+            self.selected[TRACKS] = 0;
+            self.refresh_tracks();
+        }
+    }
+
+    // regroup everything, keeping what was selected where it still exists
+    fn rebuild(&mut self) {
+        let group = self.groups.get(self.selected[GROUPS]).cloned();
+        let album = self.albums.get(self.selected[ALBUMS]).cloned();
+        let track = self
+            .tracks
+            .get(self.selected[TRACKS])
+            .map(|&i| self.songs[i].0.clone());
+
+        let mut groups: Vec<String> = self
+            .songs
+            .iter()
+            .map(|(_, song)| self.grouping.key(song))
+            .collect();
+        groups.sort_by_key(|group| group.to_lowercase());
+        groups.dedup_by(|a, b| same_key(a, b));
+        self.groups = groups;
+        self.selected[GROUPS] = group
+            .and_then(|group| self.groups.iter().position(|g| same_key(g, &group)))
+            .unwrap_or(0);
+
+        self.refresh_albums();
+        self.selected[ALBUMS] = album
+            .and_then(|album| self.albums.iter().position(|a| same_key(a, &album)))
+            .unwrap_or(0);
+
+        self.refresh_tracks();
+        self.selected[TRACKS] = track
+            .and_then(|track| self.tracks.iter().position(|&i| self.songs[i].0 == track))
+            .unwrap_or(0);
+    }
+
+    fn refresh_albums(&mut self) {
+        let mut albums: Vec<String> = match self.groups.get(self.selected[GROUPS]) {
+            Some(group) => self
+                .songs_in(group, None)
+                .into_iter()
+                .map(|i| album_key(&self.songs[i].1))
+                .collect(),
+            None => Vec::new(),
+        };
+        albums.dedup_by(|a, b| same_key(a, b));
+        self.albums = albums;
+    }
+
+    fn refresh_tracks(&mut self) {
+        self.tracks = match (
+            self.groups.get(self.selected[GROUPS]),
+            self.albums.get(self.selected[ALBUMS]),
+        ) {
+            (Some(group), Some(album)) => self.songs_in(group, Some(album)),
+            _ => Vec::new(),
+        };
+    }
+
+    // songs of `group`, optionally only those on `album`, sorted by album then track number
+    fn songs_in(&self, group: &str, album: Option<&str>) -> Vec<usize> {
+        let mut songs: Vec<usize> = (0..self.songs.len())
+            .filter(|&i| {
+                let song = &self.songs[i].1;
+                same_key(&self.grouping.key(song), group)
+                    && album.is_none_or(|album| same_key(&album_key(song), album))
+            })
+            .collect();
+
+        songs.sort_by_key(|&i| {
+            let (path, song) = &self.songs[i];
+            (
+                album_key(song).to_lowercase(),
+                song.disc.unwrap_or(1),
+                song.track.unwrap_or(0),
+                path.clone(),
+            )
+        });
+        songs
+    }
+}
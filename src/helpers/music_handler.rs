@@ -1,24 +1,119 @@
 use std::{
-    fs::File,
-    io::BufReader,
     path::{Path, PathBuf},
-    sync::{Arc, Mutex},
-    thread,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
     time::Duration,
 };
 
-use lofty::{AudioFile, Probe};
-use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink};
+use lofty::{Probe, TaggedFileExt};
+use rodio::{
+    source::{Amplify, Stoppable},
+    OutputStream, OutputStreamHandle, Sink, Source,
+};
+
+use super::{
+    crossfade::Fade,
+    decoder::Decoder,
+    gen_funcs, library,
+    replaygain::{self, ReplayGainMode},
+};
+
+/// Part of the current song played over and over, marked by point A and then point B.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum AbLoop {
+    #[default]
+    Off,
+    /// Only the start is marked so far.
+    A(Duration),
+    Looping {
+        a: Duration,
+        b: Duration,
+    },
+}
+
+// a song appended to the sink
+struct Track {
+    path: PathBuf,
+    length: Duration,
+    controls: Arc<Controls>,
+}
+
+// shared between a song's source on the audio thread and the handle
+#[derive(Default)]
+struct Controls {
+    // ReplayGain level, fixed for the life of the song
+    gain: f32,
+    time_played: Mutex<Duration>,
+    seek_request: Mutex<Option<Duration>>,
+    started: AtomicBool,
+    cancelled: AtomicBool,
+    fade: Mutex<Option<Fade>>,
+    ab_loop: Mutex<AbLoop>,
+    // what went wrong on the audio thread, waiting to be shown
+    error: Mutex<Option<String>>,
+}
 
-use super::gen_funcs;
+impl Controls {
+    // called from the audio thread every few milliseconds of playback
+    fn update(&self, src: &mut Stoppable<Amplify<Decoder>>) {
+        if self.cancelled.load(Ordering::Relaxed) {
+            src.stop();
+            return;
+        }
+        self.started.store(true, Ordering::Relaxed);
+
+        let decoder = src.inner_mut().inner_mut();
+        if let Some(pos) = self.seek_request.lock().unwrap().take() {
+            if let Err(err) = decoder.seek(pos) {
+                *self.error.lock().unwrap() = Some(seek_error(pos, err));
+            }
+        }
+        let mut elapsed = decoder.elapsed();
+        // jumping back here rather than from the handle keeps the loop free of gaps
+        if let AbLoop::Looping { a, b } = *self.ab_loop.lock().unwrap() {
+            if elapsed >= b {
+                if let Err(err) = decoder.seek(a) {
+                    *self.error.lock().unwrap() = Some(seek_error(a, err));
+                }
+                elapsed = decoder.elapsed();
+            }
+        }
+        *self.time_played.lock().unwrap() = elapsed;
+
+        let mut factor = self.gain;
+        if let Some(fade) = *self.fade.lock().unwrap() {
+            if fade.finished(elapsed) {
+                src.stop();
+                return;
+            }
+            factor *= fade.gain(elapsed);
+        }
+        src.inner_mut().set_factor(factor);
+    }
+}
+
+fn seek_error(pos: Duration, err: impl std::fmt::Display) -> String {
+    let secs = pos.as_secs();
+    format!("Could not seek to {}:{:02}: {err}", secs / 60, secs % 60)
+}
 
 pub struct MusicHandle {
-    music_output: Arc<(OutputStream, OutputStreamHandle)>,
+    music_output: (OutputStream, OutputStreamHandle),
     sink: Arc<Sink>,
-    song_length: u32,
-    time_played: Arc<Mutex<u32>>,
+    current: Option<Track>,
+    // preloaded behind `current` in the same sink so playback continues without a gap
+    next: Option<Track>,
+    // previous song's sink, still fading out under the current one
+    fading: Option<Arc<Sink>>,
+    crossfade: Duration,
+    replaygain: ReplayGainMode,
+    preamp: f32,
     currently_playing: String,
     volume: f32,
+    // turns the volume down further without changing it, 1 leaves it as it is
+    attenuation: f32,
 }
 
 impl Default for MusicHandle {
@@ -30,12 +125,17 @@ impl Default for MusicHandle {
 impl MusicHandle {
     pub fn new() -> Self {
         Self {
-            music_output: Arc::new(OutputStream::try_default().unwrap()),
+            music_output: OutputStream::try_default().unwrap(),
             sink: Arc::new(Sink::new_idle().0), // more efficient way, shouldnt have to do twice?
-            song_length: 0,
-            time_played: Arc::new(Mutex::new(0)),
+            current: None,
+            next: None,
+            fading: None,
+            crossfade: Duration::ZERO,
+            replaygain: ReplayGainMode::Off,
+            preamp: 0.,
             currently_playing: "CURRENT SONG".to_string(),
             volume: 1.,
+            attenuation: 1.,
         }
     }
 
@@ -43,111 +143,303 @@ impl MusicHandle {
         self.currently_playing.clone()
     }
 
-    pub fn song_length(&self) -> u32 {
-        self.song_length
+    pub fn song_length(&self) -> Duration {
+        match &self.current {
+            Some(track) => track.length,
+            None => Duration::ZERO,
+        }
     }
 
-    pub fn time_played(&self) -> u32 {
-        *self.time_played.lock().unwrap()
+    /// Position in the current song, derived from the samples played so far.
+    pub fn time_played(&self) -> Duration {
+        match &self.current {
+            Some(track) => *track.controls.time_played.lock().unwrap(),
+            None => Duration::ZERO,
+        }
+    }
+
+    pub fn time_remaining(&self) -> Duration {
+        self.song_length().saturating_sub(self.time_played())
+    }
+
+    pub fn crossfade(&self) -> Duration {
+        self.crossfade
+    }
+
+    /// Overlap between songs, `Duration::ZERO` plays them gaplessly instead.
+    pub fn set_crossfade(&mut self, crossfade: Duration) {
+        self.crossfade = crossfade;
+    }
+
+    /// Level songs by their ReplayGain tags, `preamp` is added to the tagged gain in decibels.
+    /// Applies from the next song appended.
+    pub fn set_replaygain(&mut self, mode: ReplayGainMode, preamp: f32) {
+        self.replaygain = mode;
+        self.preamp = preamp;
     }
 
     pub fn sink_empty(&self) -> bool {
         self.sink.empty()
     }
 
-    pub fn set_time_played(&mut self, t: u32) {
-        *self.time_played.lock().unwrap() = t;
-    }
     // set currently playing song
-    pub fn set_currently_playing(&mut self, path: &PathBuf) {
+    pub fn set_currently_playing(&mut self, path: &Path) {
         self.currently_playing = gen_funcs::audio_display(path);
     }
 
     // update current song and play
-    pub fn play(&mut self, path: PathBuf) {
+    pub fn play(&mut self, path: PathBuf) -> Result<(), String> {
+        self.load(path, false)
+    }
+
+    /// Make `path` the current song, paused `position` into it, so nothing is heard before
+    /// playback is resumed.
+    pub fn load_paused(&mut self, path: PathBuf, position: Duration) -> Result<(), String> {
+        self.load(path, true)?;
+        self.seek_to(position);
+        Ok(())
+    }
+
+    // nothing is left playing when the song can't be
+    fn load(&mut self, path: PathBuf, paused: bool) -> Result<(), String> {
         // if song already playing, need to be able to restart tho
         self.sink.stop();
-        *self.time_played.lock().unwrap() = 0;
-
-        // set currently playing
-        self.currently_playing = path
-            .clone()
-            .file_name()
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .to_string();
+        self.fading = None;
+        self.next = None;
+
+        self.sink = self.new_sink();
+        // a paused sink doesn't pull any samples, so the seek is in place before the first one
+        if paused {
+            self.sink.pause();
+        }
+        self.current = None;
+        let track = self.append(&self.sink, path)?;
+        self.set_currently_playing(&track.path);
+        self.current = Some(track);
+        Ok(())
+    }
+
+    /// Start `path` on a fresh sink, fading it in while the current song fades out underneath.
+    /// When `path` can't be played the current song carries on as if nothing happened.
+    pub fn crossfade_to(&mut self, path: PathBuf) -> Result<(), String> {
+        let sink = self.new_sink();
+        let track = self.append(&sink, path)?;
+        *track.controls.fade.lock().unwrap() = Some(Fade::In {
+            length: self.crossfade,
+        });
+
+        self.cancel_next();
+        if let Some(current) = &self.current {
+            *current.controls.fade.lock().unwrap() = Some(Fade::Out {
+                from: *current.controls.time_played.lock().unwrap(),
+                length: self.crossfade,
+            });
+        }
+        // dropping a sink stops it, keep the old one around until it has faded out
+        self.fading = Some(std::mem::replace(&mut self.sink, sink));
+
+        self.set_currently_playing(&track.path);
+        self.current = Some(track);
+        Ok(())
+    }
+
+    // reinitialize due to rodio crate
+    fn new_sink(&self) -> Arc<Sink> {
+        let sink = Arc::new(Sink::try_new(&self.music_output.1).unwrap());
+        sink.set_volume(self.volume * self.attenuation);
+        sink
+    }
+
+    /// Append `path` behind the current song so it starts on the exact sample the current one ends.
+    /// Fails if the song can't be played, leaving nothing preloaded.
+    pub fn preload(&mut self, path: PathBuf) -> Result<(), String> {
+        if self.next.is_none() && !self.sink.empty() {
+            self.next = Some(self.append(&self.sink, path)?);
+        }
+        Ok(())
+    }
+
+    /// The song being played, if any.
+    pub fn current_song(&self) -> Option<&PathBuf> {
+        self.current.as_ref().map(|t| &t.path)
+    }
+
+    /// The preloaded song, if any.
+    pub fn next_song(&self) -> Option<&PathBuf> {
+        self.next.as_ref().map(|t| &t.path)
+    }
+
+    /// Drop the preloaded song, it ends itself as soon as the sink reaches it.
+    pub fn cancel_next(&mut self) {
+        if let Some(track) = self.next.take() {
+            track.controls.cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Once the preloaded song has started playing make it the current one, returning its path.
+    pub fn advance(&mut self) -> Option<PathBuf> {
+        if !self.next.as_ref()?.controls.started.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        self.current = self.next.take();
+        let path = self.current.as_ref()?.path.clone();
         self.set_currently_playing(&path);
-        self.update_song_length(&path);
+        Some(path)
+    }
 
-        // reinitialize due to rodio crate
-        self.sink = Arc::new(Sink::try_new(&self.music_output.1).unwrap());
+    // decode path and queue it on `sink`
+    fn append(&self, sink: &Sink, path: PathBuf) -> Result<Track, String> {
+        let source = Decoder::new(&path)
+            .map_err(|err| format!("Could not play {}: {err}", path.display()))?;
 
-        // clone sink for thread
-        let sclone = self.sink.clone();
+        let length = match library::song(&path) {
+            Some(song) => song.duration,
+            None => return Err(format!("Could not read tags of {}", path.display())),
+        };
 
-        let tpclone = self.time_played.clone();
+        // ReplayGain values aren't part of the library, the tags are only read when they're used
+        let gain = match self.replaygain {
+            ReplayGainMode::Off => 1.,
+            mode => match Probe::open(&path).and_then(|probe| probe.read()) {
+                Ok(tagged_file) => match tagged_file.primary_tag().or(tagged_file.first_tag()) {
+                    Some(tag) => replaygain::gain(tag, mode, self.preamp),
+                    None => 1.,
+                },
+                Err(_) => 1.,
+            },
+        };
 
-        let _t1 = thread::spawn(move || {
-            // can send in through function
-            let file = BufReader::new(File::open(path).unwrap());
-            let source = Decoder::new(file).unwrap();
+        let track = Track {
+            path,
+            length,
+            controls: Arc::new(Controls {
+                gain,
+                ..Default::default()
+            }),
+        };
 
-            // Arc inside a thread inside a thread. BOOM, INCEPTION
-            let sink_clone_2 = sclone.clone();
-            let tpclone2 = tpclone.clone();
+        // position comes from the samples the sink actually pulled, so pausing and seeking
+        // are accounted for without a separate timer
+        let controls = track.controls.clone();
+        let source = source
+            .amplify(gain)
+            .stoppable()
+            .periodic_access(Duration::from_millis(5), move |src| controls.update(src));
 
-            sclone.append(source);
+        sink.append(source);
+        Ok(track)
+    }
 
-            let _ = thread::spawn(move || {
-                // sleep for 1 second then increment count
-                while sink_clone_2.len() == 1 {
-                    thread::sleep(Duration::from_secs(1));
+    /// What last went wrong playing the current song, if anything, taking it so it's only
+    /// reported once.
+    pub fn take_error(&self) -> Option<String> {
+        let track = self.current.as_ref()?;
+        track.controls.error.lock().unwrap().take()
+    }
 
-                    if !sink_clone_2.is_paused() {
-                        *tpclone2.lock().unwrap() += 1;
-                    }
-                }
-            });
-            // if sink.stop, thread destroyed.
-            sclone.sleep_until_end();
-        });
+    pub fn is_paused(&self) -> bool {
+        self.sink.is_paused()
     }
 
     pub fn play_pause(&mut self) {
-        if self.sink.is_paused() {
-            self.sink.play()
-        } else {
-            self.sink.pause()
+        let paused = self.sink.is_paused();
+        for sink in self.sinks() {
+            if paused {
+                sink.play()
+            } else {
+                sink.pause()
+            }
         }
     }
 
     pub fn skip(&self) {
-        self.sink.stop();
+        for sink in self.sinks() {
+            sink.stop();
+        }
+    }
+
+    // the current sink and, during a crossfade, the one fading out
+    fn sinks(&self) -> impl Iterator<Item = &Arc<Sink>> {
+        std::iter::once(&self.sink).chain(&self.fading)
     }
 
-    /// Update `self.song_length` with the provided file.
-    pub fn update_song_length(&mut self, path: &PathBuf) {
-        let path = Path::new(&path);
-        let tagged_file = Probe::open(path)
-            .expect("ERROR: Bad path provided!")
-            .read()
-            .expect("ERROR: Failed to read file!");
+    /// Jump `offset` seconds forward, or backward if negative, in the current song.
+    pub fn seek(&mut self, offset: i32) {
+        let offset_abs = Duration::from_secs(offset.unsigned_abs() as u64);
+        let pos = if offset < 0 {
+            self.time_played().saturating_sub(offset_abs)
+        } else {
+            self.time_played() + offset_abs
+        };
+        self.seek_to(pos);
+    }
 
-        let properties = &tagged_file.properties();
-        let duration = properties.duration();
+    /// Jump to `pos` from the start of the current song.
+    pub fn seek_to(&mut self, pos: Duration) {
+        if self.sink.empty() {
+            return;
+        }
 
-        // update song length, currently playing
-        self.song_length = duration.as_secs() as u32;
+        let Some(track) = &self.current else {
+            return;
+        };
+
+        let pos = pos.min(track.length);
+        *track.controls.seek_request.lock().unwrap() = Some(pos);
+        // show the jump right away instead of waiting for the sink to pull the next samples
+        *track.controls.time_played.lock().unwrap() = pos;
     }
 
-    pub fn change_volume(&mut self, volume: f32) {
-        self.volume += volume;
-        if self.volume < 0. {
-            self.volume = 0.;
-        } else if self.volume > 1. {
-            self.volume = 1.;
+    pub fn ab_loop(&self) -> AbLoop {
+        match &self.current {
+            Some(track) => *track.controls.ab_loop.lock().unwrap(),
+            None => AbLoop::Off,
+        }
+    }
+
+    /// Mark point A at the current position, then point B to start looping, then stop looping.
+    /// Marking B before A swaps them. A new song starts without a loop.
+    pub fn cycle_ab_loop(&mut self) {
+        if self.sink.empty() {
+            return;
         }
-        self.sink.set_volume(self.volume)
+        let Some(track) = &self.current else {
+            return;
+        };
+
+        let now = *track.controls.time_played.lock().unwrap();
+        let mut ab_loop = track.controls.ab_loop.lock().unwrap();
+        *ab_loop = match *ab_loop {
+            AbLoop::Off => AbLoop::A(now),
+            // nothing to loop yet
+            AbLoop::A(a) if a == now => AbLoop::A(a),
+            AbLoop::A(a) => AbLoop::Looping {
+                a: a.min(now),
+                b: a.max(now),
+            },
+            AbLoop::Looping { .. } => AbLoop::Off,
+        };
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.volume
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0., 1.);
+        for sink in self.sinks() {
+            sink.set_volume(self.volume * self.attenuation)
+        }
+    }
+
+    /// Play at `attenuation` times the volume, for fading out without touching the volume itself.
+    pub fn set_attenuation(&mut self, attenuation: f32) {
+        self.attenuation = attenuation.clamp(0., 1.);
+        self.set_volume(self.volume);
+    }
+
+    pub fn change_volume(&mut self, volume: f32) {
+        self.set_volume(self.volume + volume);
     }
 }
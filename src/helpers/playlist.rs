@@ -0,0 +1,561 @@
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use super::{gen_funcs, library};
+
+const PLAYLIST_EXTENSIONS: [&str; 4] = ["m3u", "m3u8", "pls", "xspf"];
+
+/// A song listed in a playlist.
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub path: PathBuf,
+    /// Title and length the playlist gives the song, if any. Used for songs whose tags don't
+    /// have them.
+    pub title: Option<String>,
+    pub duration: Option<Duration>,
+}
+
+/// Whether the extension is one of the playlist formats that can be read.
+pub fn is_playlist(path: &Path) -> bool {
+    extension(path).is_some_and(|ext| PLAYLIST_EXTENSIONS.contains(&ext.as_str()))
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .map(str::to_ascii_lowercase)
+}
+
+/// The songs listed in the playlist at `path`. Relative locations are taken from the folder
+/// the playlist is in, streams are skipped.
+pub fn read(path: &Path) -> io::Result<Vec<Entry>> {
+    let bytes = fs::read(path)?;
+    // .m3u8 and .xspf are always UTF-8, plain .m3u and .pls often Latin-1
+    let content = match String::from_utf8(bytes) {
+        Ok(content) => content,
+        Err(err) => err.into_bytes().iter().map(|&b| b as char).collect(),
+    };
+    let content = content.trim_start_matches('\u{feff}');
+    let dir = path.parent().unwrap_or(Path::new(""));
+
+    let entries = match extension(path).as_deref() {
+        Some("pls") => read_pls(content, dir),
+        Some("xspf") => read_xspf(content, dir),
+        _ => read_m3u(content, dir),
+    };
+    for entry in &entries {
+        library::add_playlist_info(&entry.path, entry.title.clone(), entry.duration);
+    }
+
+    Ok(entries)
+}
+
+/// Entries whose files no longer exist.
+pub fn missing(entries: &[Entry]) -> Vec<&Entry> {
+    entries
+        .iter()
+        .filter(|entry| !entry.path.is_file())
+        .collect()
+}
+
+/// Save `songs` as a playlist at `path`, in the format its extension names. Songs in the
+/// playlist's folder or below are written relative to it so the folder can be moved as a whole.
+pub fn write(path: &Path, songs: &[PathBuf]) -> io::Result<()> {
+    let dir = path.parent().unwrap_or(Path::new(""));
+
+    let content = match extension(path).as_deref() {
+        Some("pls") => write_pls(songs, dir),
+        Some("xspf") => write_xspf(songs, dir),
+        _ => write_m3u(songs, dir),
+    };
+
+    fs::write(path, content)
+}
+
+// where a song listed as `location` is, None for streams
+fn resolve(location: &str, dir: &Path) -> Option<PathBuf> {
+    if let Some(uri) = location.strip_prefix("file://") {
+        // file://localhost/music/song.mp3, the host is usually left out
+        let path = &uri[uri.find('/')?..];
+        return Some(PathBuf::from(percent_decode(path)));
+    }
+    if location.contains("://") {
+        return None;
+    }
+
+    Some(dir.join(location))
+}
+
+// length in whole seconds, -1 when unknown
+fn length(song: &Path) -> i64 {
+    library::song(song)
+        .map(|song| song.duration.as_secs() as i64)
+        .unwrap_or(-1)
+}
+
+fn relative<'a>(song: &'a Path, dir: &Path) -> &'a Path {
+    song.strip_prefix(dir).unwrap_or(song)
+}
+
+fn read_m3u(content: &str, dir: &Path) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    // #EXTINF describes the location on the line after it
+    let mut info = None;
+
+    for line in content.lines() {
+        let line = line.trim();
+
+        if let Some(extinf) = line.strip_prefix("#EXTINF:") {
+            info = Some(parse_extinf(extinf));
+        } else if line.is_empty() || line.starts_with('#') {
+            continue;
+        } else {
+            let (duration, title) = info.take().unwrap_or((None, None));
+            let Some(path) = resolve(line, dir) else {
+                continue;
+            };
+
+            entries.push(Entry {
+                path,
+                title,
+                duration,
+            });
+        }
+    }
+
+    entries
+}
+
+// "123,Artist - Title", the length can be followed by attributes and is -1 when unknown
+fn parse_extinf(extinf: &str) -> (Option<Duration>, Option<String>) {
+    let (length, title) = extinf.split_once(',').unwrap_or((extinf, ""));
+    let duration = length
+        .split_whitespace()
+        .next()
+        .and_then(|secs| secs.parse::<f64>().ok())
+        .filter(|secs| *secs >= 0.)
+        .map(Duration::from_secs_f64);
+    let title = Some(title.trim().to_string()).filter(|title| !title.is_empty());
+
+    (duration, title)
+}
+
+fn write_m3u(songs: &[PathBuf], dir: &Path) -> String {
+    let mut content = String::from("#EXTM3U\n");
+
+    for song in songs {
+        content.push_str(&format!(
+            "#EXTINF:{length},{title}\n{location}\n",
+            length = length(song),
+            title = gen_funcs::audio_display(song),
+            location = relative(song, dir).display(),
+        ));
+    }
+
+    content
+}
+
+// keys of one numbered entry of a .pls file, the file is required
+#[derive(Default)]
+struct PlsEntry {
+    file: Option<String>,
+    title: Option<String>,
+    duration: Option<Duration>,
+}
+
+// an ini file with numbered File, Title and Length keys under [playlist]
+fn read_pls(content: &str, dir: &Path) -> Vec<Entry> {
+    let mut entries: BTreeMap<usize, PlsEntry> = BTreeMap::new();
+
+    for line in content.lines() {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim().to_string();
+
+        // the number is where the key's name ends
+        let split = key.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+        let Ok(n) = key[split..].parse::<usize>() else {
+            continue;
+        };
+        let entry = entries.entry(n).or_default();
+
+        match &key[..split] {
+            "file" => entry.file = Some(value),
+            "title" => entry.title = Some(value).filter(|title| !title.is_empty()),
+            "length" => {
+                entry.duration = value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|secs| *secs >= 0.)
+                    .map(Duration::from_secs_f64)
+            }
+            _ => {}
+        }
+    }
+
+    entries
+        .into_values()
+        .filter_map(|entry| {
+            Some(Entry {
+                path: resolve(&entry.file?, dir)?,
+                title: entry.title,
+                duration: entry.duration,
+            })
+        })
+        .collect()
+}
+
+fn write_pls(songs: &[PathBuf], dir: &Path) -> String {
+    let mut content = String::from("[playlist]\n");
+
+    for (n, song) in songs.iter().enumerate() {
+        content.push_str(&format!(
+            "File{n}={location}\nTitle{n}={title}\nLength{n}={length}\n",
+            n = n + 1,
+            location = relative(song, dir).display(),
+            title = gen_funcs::audio_display(song),
+            length = length(song),
+        ));
+    }
+    content.push_str(&format!("NumberOfEntries={}\nVersion=2\n", songs.len()));
+
+    content
+}
+
+// XML with a <track> per song, only the few elements kronos cares about are picked out
+fn read_xspf(content: &str, dir: &Path) -> Vec<Entry> {
+    let Some(track_list) = element(content, "trackList") else {
+        return Vec::new();
+    };
+
+    elements(&track_list, "track")
+        .into_iter()
+        .filter_map(|track| {
+            let location = element(track, "location")?;
+            // relative locations are URIs too
+            let location = if location.contains("://") {
+                location
+            } else {
+                percent_decode(&location)
+            };
+            let title = element(track, "title");
+            let title = match element(track, "creator") {
+                Some(creator) => title.map(|title| format!("{creator} - {title}")),
+                None => title,
+            };
+            let duration = element(track, "duration")
+                .and_then(|ms| ms.parse::<u64>().ok())
+                .map(Duration::from_millis);
+
+            Some(Entry {
+                path: resolve(&location, dir)?,
+                title,
+                duration,
+            })
+        })
+        .collect()
+}
+
+// text of the first <name> element in `xml`
+fn element(xml: &str, name: &str) -> Option<String> {
+    let text = elements(xml, name).into_iter().next()?;
+    Some(xml_unescape(text.trim()))
+}
+
+// raw contents of every <name> element in `xml`, the opening tags can have attributes
+fn elements<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    let open = format!("<{name}");
+    let close = format!("</{name}>");
+    let mut found = Vec::new();
+    let mut rest = xml;
+
+    while let Some(start) = rest.find(&open) {
+        let tag = &rest[start + open.len()..];
+        // <track> or <track xml:id="1">, but not <trackList>
+        if !tag.starts_with(|c: char| c == '>' || c.is_whitespace()) {
+            rest = tag;
+            continue;
+        }
+        let Some(end) = tag.find('>') else {
+            break;
+        };
+        let content = &tag[end + 1..];
+        let Some(len) = content.find(&close) else {
+            break;
+        };
+
+        found.push(&content[..len]);
+        rest = &content[len + close.len()..];
+    }
+
+    found
+}
+
+fn write_xspf(songs: &[PathBuf], dir: &Path) -> String {
+    let mut content = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n  <trackList>\n",
+    );
+
+    for song in songs {
+        let location = relative(song, dir).to_string_lossy();
+        // absolute paths need the scheme, relative ones are resolved like any relative URI
+        let location = if location.starts_with('/') {
+            format!("file://{}", percent_encode(&location))
+        } else {
+            percent_encode(&location)
+        };
+
+        content.push_str("    <track>\n");
+        content.push_str(&format!(
+            "      <location>{}</location>\n",
+            xml_escape(&location)
+        ));
+        if let Some(song) = library::song(song) {
+            if let Some(title) = song.title {
+                content.push_str(&format!("      <title>{}</title>\n", xml_escape(&title)));
+            }
+            if let Some(artist) = song.artist {
+                content.push_str(&format!(
+                    "      <creator>{}</creator>\n",
+                    xml_escape(&artist)
+                ));
+            }
+            content.push_str(&format!(
+                "      <duration>{}</duration>\n",
+                song.duration.as_millis()
+            ));
+        }
+        content.push_str("    </track>\n");
+    }
+    content.push_str("  </trackList>\n</playlist>\n");
+
+    content
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn xml_unescape(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let entity = rest.find(';').map(|end| (&rest[1..end], end));
+        let c = entity.and_then(|(name, _)| match name {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => match name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => name.strip_prefix('#').and_then(|dec| dec.parse().ok()),
+            }
+            .and_then(char::from_u32),
+        });
+
+        match (c, entity) {
+            (Some(c), Some((_, end))) => {
+                unescaped.push(c);
+                rest = &rest[end + 1..];
+            }
+            // a stray ampersand, keep it as is
+            _ => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+
+    unescaped
+}
+
+// URIs only allow a few characters as they are, everything else is written as %XX per byte
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match hex {
+            Some(b) => {
+                decoded.push(b);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a fresh folder for the playlist, the songs in it don't have to exist
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("kronos-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn songs(dir: &Path) -> Vec<PathBuf> {
+        vec![
+            dir.join("album/01 plain.mp3"),
+            // written relative to the playlist, with characters that need escaping
+            dir.join("album/02 100% & <more> #1 \"quoted\".flac"),
+            dir.join("Ünïcode ♫/03.ogg"),
+            // outside the playlist's folder, so written as an absolute path
+            PathBuf::from("/somewhere else/04 song?.wav"),
+        ]
+    }
+
+    fn round_trip(extension: &str) {
+        let dir = temp_dir(extension);
+        let path = dir.join(format!("list.{extension}"));
+        let songs = songs(&dir);
+
+        write(&path, &songs).unwrap();
+        let entries = read(&path).unwrap();
+        let paths: Vec<PathBuf> = entries.into_iter().map(|entry| entry.path).collect();
+        assert_eq!(paths, songs);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn m3u_round_trips() {
+        round_trip("m3u");
+    }
+
+    #[test]
+    fn m3u8_round_trips() {
+        round_trip("m3u8");
+    }
+
+    #[test]
+    fn pls_round_trips() {
+        round_trip("pls");
+    }
+
+    #[test]
+    fn xspf_round_trips() {
+        round_trip("xspf");
+    }
+
+    #[test]
+    fn m3u_reads_extinf() {
+        let entries = read_m3u(
+            "#EXTM3U\n#EXTINF:123,Artist - Title\nsong.mp3\n\nhttp://radio/stream\nother.mp3\n",
+            Path::new("/music"),
+        );
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].path, Path::new("/music/song.mp3"));
+        assert_eq!(entries[0].title.as_deref(), Some("Artist - Title"));
+        assert_eq!(entries[0].duration, Some(Duration::from_secs(123)));
+        assert_eq!(entries[1].path, Path::new("/music/other.mp3"));
+        assert_eq!(entries[1].title, None);
+    }
+
+    #[test]
+    fn pls_reads_numbered_entries_in_order() {
+        let entries = read_pls(
+            "[playlist]\nFile2=b.mp3\nTitle2=B\nFile1=a.mp3\nLength1=-1\nNumberOfEntries=2\n",
+            Path::new("/music"),
+        );
+
+        let paths: Vec<&Path> = entries.iter().map(|entry| entry.path.as_path()).collect();
+        assert_eq!(
+            paths,
+            [Path::new("/music/a.mp3"), Path::new("/music/b.mp3")]
+        );
+        assert_eq!(entries[0].duration, None);
+        assert_eq!(entries[1].title.as_deref(), Some("B"));
+    }
+
+    #[test]
+    fn xspf_reads_tracks_with_attributes() {
+        let entries = read_xspf(
+            r#"<playlist version="1" xmlns="http://xspf.org/ns/0/">
+              <trackList>
+                <track xml:id="one">
+                  <location>file://localhost/music/a%20b.mp3</location>
+                  <title>Rock &amp; Roll</title>
+                  <creator>AC&#47;DC</creator>
+                  <duration>61500</duration>
+                </track>
+                <track><location>sub/c%23.mp3</location></track>
+                <track>
+                  <location>http://radio/stream</location>
+                </track>
+              </trackList>
+            </playlist>"#,
+            Path::new("/lists"),
+        );
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].path, Path::new("/music/a b.mp3"));
+        assert_eq!(entries[0].title.as_deref(), Some("AC/DC - Rock & Roll"));
+        assert_eq!(entries[0].duration, Some(Duration::from_millis(61500)));
+        assert_eq!(entries[1].path, Path::new("/lists/sub/c#.mp3"));
+    }
+
+    #[test]
+    fn xml_escaping_round_trips() {
+        let text = r#"<a href="x">Tom & Jerry's</a>"#;
+        assert_eq!(
+            xml_escape(text),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry's&lt;/a&gt;"
+        );
+        assert_eq!(xml_unescape(&xml_escape(text)), text);
+        assert_eq!(xml_unescape("&#x41;&#66;&apos; & &bogus;"), "AB' & &bogus;");
+    }
+
+    #[test]
+    fn percent_encoding_round_trips() {
+        let text = "/music/100% ♫ & #1?.mp3";
+        let encoded = percent_encode(text);
+        assert_eq!(encoded, "/music/100%25%20%E2%99%AB%20%26%20%231%3F.mp3");
+        assert_eq!(percent_decode(&encoded), text);
+        // a stray percent sign is kept as it is
+        assert_eq!(percent_decode("100%"), "100%");
+    }
+}
@@ -0,0 +1,243 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use tui::widgets::ListState;
+
+use super::{gen_funcs, playlist};
+
+// saved playlists are always written in this format
+const EXTENSION: &str = "m3u8";
+
+/// Playlists saved by name in one folder, with the songs of the highlighted one. The songs can
+/// be edited in place, which changes the file but not the queue.
+pub struct PlaylistManager {
+    dir: PathBuf,
+    names: Vec<String>,
+    selected: usize,
+    // songs of the selected playlist
+    songs: Vec<PathBuf>,
+    song_selected: usize,
+    // whether the songs have focus rather than the playlists
+    editing: bool,
+}
+
+impl PlaylistManager {
+    pub fn new(dir: PathBuf) -> Self {
+        let mut manager = Self {
+            dir,
+            names: Vec::new(),
+            selected: 0,
+            songs: Vec::new(),
+            song_selected: 0,
+            editing: false,
+        };
+        manager.refresh(None);
+        manager
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn selected_name(&self) -> Option<&str> {
+        self.names.get(self.selected).map(String::as_str)
+    }
+
+    pub fn songs(&self) -> &[PathBuf] {
+        &self.songs
+    }
+
+    pub fn selected_song(&self) -> Option<&PathBuf> {
+        self.songs.get(self.song_selected)
+    }
+
+    pub fn editing(&self) -> bool {
+        self.editing
+    }
+
+    pub fn state(&self) -> ListState {
+        let mut state = ListState::default();
+        if !self.names.is_empty() {
+            state.select(Some(self.selected));
+        }
+        state
+    }
+
+    pub fn song_state(&self) -> ListState {
+        let mut state = ListState::default();
+        if !self.songs.is_empty() {
+            state.select(Some(self.song_selected));
+        }
+        state
+    }
+
+    pub fn next(&mut self) {
+        if self.editing {
+            if !self.songs.is_empty() {
+                self.song_selected = (self.song_selected + 1) % self.songs.len();
+            }
+        } else if !self.names.is_empty() {
+            self.select((self.selected + 1) % self.names.len());
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if self.editing {
+            if !self.songs.is_empty() {
+                let len = self.songs.len();
+                self.song_selected = (self.song_selected + len - 1) % len;
+            }
+        } else if !self.names.is_empty() {
+            let len = self.names.len();
+            self.select((self.selected + len - 1) % len);
+        }
+    }
+
+    /// Move focus to the songs of the selected playlist.
+    pub fn edit(&mut self) {
+        if !self.names.is_empty() {
+            self.editing = true;
+        }
+    }
+
+    /// Move focus back to the playlists.
+    pub fn stop_editing(&mut self) {
+        self.editing = false;
+    }
+
+    /// Save `songs` as the playlist `name`, replacing any playlist of that name.
+    pub fn save(&mut self, name: &str, songs: &[PathBuf]) -> io::Result<()> {
+        let path = self.path(name)?;
+        fs::create_dir_all(&self.dir)?;
+        playlist::write(&path, songs)?;
+        self.refresh(Some(name));
+        Ok(())
+    }
+
+    pub fn rename(&mut self, name: &str, new_name: &str) -> io::Result<()> {
+        if name == new_name {
+            return Ok(());
+        }
+        let new_path = self.path(new_name)?;
+        if new_path.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("there already is a playlist called {new_name}"),
+            ));
+        }
+        fs::rename(self.path(name)?, new_path)?;
+        self.refresh(Some(new_name));
+        Ok(())
+    }
+
+    pub fn delete(&mut self, name: &str) -> io::Result<()> {
+        fs::remove_file(self.path(name)?)?;
+        self.refresh(None);
+        Ok(())
+    }
+
+    /// Take the selected song out of the selected playlist. Only while the songs have focus,
+    /// as the selection isn't shown otherwise.
+    pub fn remove_song(&mut self) -> io::Result<()> {
+        let Some(name) = self.selected_name().map(str::to_string) else {
+            return Ok(());
+        };
+        if !self.editing || self.song_selected >= self.songs.len() {
+            return Ok(());
+        }
+
+        let mut songs = self.songs.clone();
+        songs.remove(self.song_selected);
+        playlist::write(&self.path(&name)?, &songs)?;
+
+        self.songs = songs;
+        self.song_selected = self.song_selected.min(self.songs.len().saturating_sub(1));
+        Ok(())
+    }
+
+    /// Move the selected song of the selected playlist to `to`, the selection follows it. Only
+    /// while the songs have focus, like `remove_song`.
+    pub fn move_song(&mut self, to: usize) -> io::Result<()> {
+        let Some(name) = self.selected_name().map(str::to_string) else {
+            return Ok(());
+        };
+        let from = self.song_selected;
+        if !self.editing || from >= self.songs.len() || to >= self.songs.len() || from == to {
+            return Ok(());
+        }
+
+        let mut songs = self.songs.clone();
+        let song = songs.remove(from);
+        songs.insert(to, song);
+        playlist::write(&self.path(&name)?, &songs)?;
+
+        self.songs = songs;
+        self.song_selected = to;
+        Ok(())
+    }
+
+    /// Position of the selected song in the selected playlist.
+    pub fn song_position(&self) -> usize {
+        self.song_selected
+    }
+
+    /// Every entry of the playlist `name`, including songs that no longer exist.
+    pub fn entries(&self, name: &str) -> io::Result<Vec<playlist::Entry>> {
+        playlist::read(&self.path(name)?)
+    }
+
+    fn path(&self, name: &str) -> io::Result<PathBuf> {
+        // names are used as file names as they are
+        if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{name:?} can't be used as a playlist name"),
+            ));
+        }
+
+        Ok(self.dir.join(format!("{name}.{EXTENSION}")))
+    }
+
+    fn select(&mut self, i: usize) {
+        self.selected = i;
+        self.song_selected = 0;
+        self.songs = match self.selected_name() {
+            Some(name) => self
+                .entries(name)
+                .map(|entries| entries.into_iter().map(|entry| entry.path).collect())
+                .unwrap_or_default(),
+            None => Vec::new(),
+        };
+    }
+
+    // read the folder again, selecting `name` or staying near the old selection
+    fn refresh(&mut self, name: Option<&str>) {
+        let mut names: Vec<String> = fs::read_dir(&self.dir)
+            .map(|dir| {
+                dir.filter_map(|entry| {
+                    let path = entry.ok()?.path();
+                    if !is_saved(&path) {
+                        return None;
+                    }
+                    Some(path.file_stem()?.to_string_lossy().to_string())
+                })
+                .collect()
+            })
+            .unwrap_or_default();
+        names.sort_by(|a, b| gen_funcs::natural_cmp(a, b));
+        self.names = names;
+
+        let i = name
+            .and_then(|name| self.names.iter().position(|n| n == name))
+            .unwrap_or(self.selected)
+            .min(self.names.len().saturating_sub(1));
+        self.editing = self.editing && !self.names.is_empty();
+        self.select(i);
+    }
+}
+
+fn is_saved(path: &Path) -> bool {
+    path.is_file() && path.extension().is_some_and(|ext| ext == EXTENSION)
+}
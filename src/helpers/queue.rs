@@ -1,19 +1,146 @@
 use std::{
-    collections::VecDeque,
+    collections::{BTreeSet, HashSet, VecDeque},
+    fmt,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
-use lofty::{AudioFile, Probe};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use serde::{Deserialize, Serialize};
 use tui::widgets::ListState;
 
-use super::gen_funcs::bulk_add;
+use super::{gen_funcs::same_album, library};
 use crate::constants::{SECONDS_PER_DAY, SECONDS_PER_HOUR, SECONDS_PER_MINUTE};
 
+// how many edits can be undone
+const UNDO_LIMIT: usize = 100;
+
+/// What happens once a song has been played.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum RepeatMode {
+    #[default]
+    Off,
+    /// Play the same song again.
+    One,
+    /// Send played songs to the back of the queue.
+    All,
+}
+
+impl RepeatMode {
+    /// Get the next mode in the cycle.
+    pub fn next(&self) -> Self {
+        match self {
+            Self::Off => Self::One,
+            Self::One => Self::All,
+            // Wrap around to the first mode.
+            Self::All => Self::Off,
+        }
+    }
+}
+
+impl fmt::Display for RepeatMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Off => write!(f, "Off"),
+            Self::One => write!(f, "One"),
+            Self::All => write!(f, "All"),
+        }
+    }
+}
+
+/// Order songs are taken from the queue in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum ShuffleMode {
+    #[default]
+    Off,
+    /// Any song at random.
+    Songs,
+    /// Albums at random, each played through in queue order.
+    Albums,
+}
+
+impl ShuffleMode {
+    /// Get the next mode in the cycle.
+    pub fn next(&self) -> Self {
+        match self {
+            Self::Off => Self::Songs,
+            Self::Songs => Self::Albums,
+            // Wrap around to the first mode.
+            Self::Albums => Self::Off,
+        }
+    }
+}
+
+impl fmt::Display for ShuffleMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Off => write!(f, "Off"),
+            Self::Songs => write!(f, "Songs"),
+            Self::Albums => write!(f, "Albums"),
+        }
+    }
+}
+
+// the queue as it was before or after an edit
+struct Snapshot {
+    items: VecDeque<PathBuf>,
+    total_time: u32,
+    marked: BTreeSet<usize>,
+    selected: Option<usize>,
+}
+
+impl Snapshot {
+    // take out a song that was played since, or send it to the back when repeating
+    fn played(&mut self, item: &Path, length: u32, repeat_all: bool) {
+        let Some(i) = self.items.iter().position(|song| song == item) else {
+            return;
+        };
+        let song = self.items.remove(i).unwrap();
+        let last = self.items.len();
+
+        self.marked = if repeat_all {
+            self.items.push_back(song);
+            self.marked
+                .iter()
+                .map(|&m| {
+                    if m == i {
+                        last
+                    } else if m > i {
+                        m - 1
+                    } else {
+                        m
+                    }
+                })
+                .collect()
+        } else {
+            self.total_time = self.total_time.saturating_sub(length);
+            self.marked
+                .iter()
+                .filter(|&&m| m != i)
+                .map(|&m| if m > i { m - 1 } else { m })
+                .collect()
+        };
+    }
+}
+
 pub struct Queue {
     state: ListState,
     items: VecDeque<PathBuf>,
     curr: usize,
     total_time: u32,
+    repeat: RepeatMode,
+    shuffle: ShuffleMode,
+    rng: StdRng,
+    // when shuffling, whether the front of the queue is the song drawn to play next
+    drawn: bool,
+    // songs played since shuffle was turned on, so repeated queues don't replay them early
+    played: HashSet<PathBuf>,
+    last_played: Option<PathBuf>,
+    // indices of the songs marked for moving or removing together
+    marked: BTreeSet<usize>,
+    // the queue before each edit, latest last, and after each edit undone
+    undo: VecDeque<Snapshot>,
+    redo: Vec<Snapshot>,
 }
 
 impl Queue {
@@ -23,6 +150,116 @@ impl Queue {
             items: VecDeque::new(),
             curr: 0,
             total_time: 0,
+            repeat: RepeatMode::Off,
+            shuffle: ShuffleMode::Off,
+            rng: StdRng::from_entropy(),
+            drawn: false,
+            played: HashSet::new(),
+            last_played: None,
+            marked: BTreeSet::new(),
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+        }
+    }
+
+    /// Make shuffling repeatable, mainly for tests.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn repeat(&self) -> RepeatMode {
+        self.repeat
+    }
+
+    pub fn set_repeat(&mut self, repeat: RepeatMode) {
+        self.repeat = repeat;
+    }
+
+    pub fn cycle_repeat(&mut self) {
+        self.repeat = self.repeat.next();
+    }
+
+    pub fn shuffle_mode(&self) -> ShuffleMode {
+        self.shuffle
+    }
+
+    pub fn set_shuffle_mode(&mut self, shuffle: ShuffleMode) {
+        self.shuffle = shuffle;
+        self.drawn = false;
+        self.played.clear();
+    }
+
+    pub fn cycle_shuffle(&mut self) {
+        self.set_shuffle_mode(self.shuffle.next());
+    }
+
+    /// Put the queue in a random order once.
+    pub fn shuffle(&mut self) {
+        if self.items.len() < 2 {
+            return;
+        }
+        self.checkpoint();
+        self.items.make_contiguous().shuffle(&mut self.rng);
+        self.drawn = false;
+        self.marked.clear();
+    }
+
+    /// The song that `pop` will return.
+    pub fn upcoming(&mut self) -> Option<&PathBuf> {
+        self.draw();
+        self.items.front()
+    }
+
+    // when shuffling, move a song that hasn't been played yet to the front of the queue
+    fn draw(&mut self) {
+        if self.shuffle == ShuffleMode::Off || self.drawn || self.items.len() < 2 {
+            return;
+        }
+
+        let mut candidates: Vec<usize> = (0..self.items.len())
+            .filter(|&i| !self.played.contains(&self.items[i]))
+            .collect();
+        if candidates.is_empty() {
+            // everything has been played, start another round
+            self.played.clear();
+            candidates = (0..self.items.len()).collect();
+        }
+
+        let mut pick = *candidates.choose(&mut self.rng).unwrap();
+        if self.shuffle == ShuffleMode::Albums {
+            let items = &self.items;
+            // carry on with the album being played, otherwise start the picked album
+            // from its first queued song
+            pick = self
+                .last_played
+                .as_ref()
+                .and_then(|last| candidates.iter().find(|&&i| same_album(last, &items[i])))
+                .or_else(|| {
+                    candidates
+                        .iter()
+                        .find(|&&i| same_album(&items[pick], &items[i]))
+                })
+                .copied()
+                .unwrap_or(pick);
+        }
+
+        let item = self.items.remove(pick).unwrap();
+        self.items.push_front(item);
+        self.drawn = true;
+
+        let moved = |i: usize| {
+            if i == pick {
+                0
+            } else if i < pick {
+                i + 1
+            } else {
+                i
+            }
+        };
+        self.remap_marks(|i| Some(moved(i)));
+        // the selection stays on the same song
+        if let Some(i) = self.state.selected() {
+            self.select(moved(i));
         }
     }
 
@@ -44,6 +281,11 @@ impl Queue {
         self.items.len()
     }
 
+    /// Length of every song in the queue together.
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(self.total_time as u64)
+    }
+
     pub fn total_time(&self) -> String {
         let days = self.total_time / SECONDS_PER_DAY;
         let hours = (self.total_time % SECONDS_PER_DAY) / SECONDS_PER_HOUR;
@@ -77,33 +319,48 @@ impl Queue {
     }
 
     pub fn pop(&mut self) -> PathBuf {
-        self.decrement_total_time();
-        self.items.pop_front().unwrap()
+        self.draw();
+        self.drawn = false;
+
+        let item = if self.repeat == RepeatMode::All {
+            // played songs go round again
+            let item = self.items.pop_front().unwrap();
+            self.items.push_back(item.clone());
+            let last = self.items.len() - 1;
+            self.remap_marks(|i| Some(i.checked_sub(1).unwrap_or(last)));
+            item
+        } else {
+            self.decrement_total_time(0);
+            self.remap_marks(|i| i.checked_sub(1));
+            self.items.pop_front().unwrap()
+        };
+
+        let length = self.item_length(&item);
+        let repeat_all = self.repeat == RepeatMode::All;
+        for snapshot in self.undo.iter_mut().chain(self.redo.iter_mut()) {
+            snapshot.played(&item, length, repeat_all);
+        }
+
+        if self.shuffle != ShuffleMode::Off {
+            self.played.insert(item.clone());
+        }
+        self.last_played = Some(item.clone());
+        item
     }
 
     pub fn state(&self) -> ListState {
         self.state.clone()
     }
 
-    fn decrement_total_time(&mut self) {
-        let item = self.items[self.curr].clone();
+    fn decrement_total_time(&mut self, index: usize) {
+        let item = self.items[index].clone();
         let length = self.item_length(&item);
-        self.total_time -= length;
+        self.total_time = self.total_time.saturating_sub(length);
     }
 
     // get audio file length
-    pub fn item_length(&mut self, path: &PathBuf) -> u32 {
-        let path = Path::new(&path);
-        let tagged_file = Probe::open(path)
-            .expect("ERROR: Bad path provided!")
-            .read()
-            .expect("ERROR: Failed to read file!");
-
-        let properties = &tagged_file.properties();
-        let duration = properties.duration();
-
-        // update song length, currently playing
-        duration.as_secs() as u32
+    pub fn item_length(&self, path: &Path) -> u32 {
+        library::song(path).map_or(0, |song| song.duration.as_secs() as u32)
     }
 
     pub fn next(&mut self) {
@@ -149,38 +406,565 @@ impl Queue {
         self.state.select(None);
     }
 
+    pub fn select(&mut self, i: usize) {
+        self.curr = i;
+        self.state.select(Some(i));
+    }
+
+    /// Add a song to the back, without it being undoable. Use `extend` for songs the user adds.
     pub fn add(&mut self, item: PathBuf) {
-        if item.is_dir() {
-            let files = bulk_add(&item);
-            for f in files {
-                let length = self.item_length(&f);
-                self.total_time += length;
-                self.items.push_back(f);
+        self.total_time += self.item_length(&item);
+        self.items.push_back(item);
+    }
+
+    /// Add songs to the back, undone together.
+    pub fn extend(&mut self, items: Vec<PathBuf>) {
+        if items.is_empty() {
+            return;
+        }
+        self.checkpoint();
+        for item in items {
+            self.add(item);
+        }
+    }
+
+    // remember the queue as it is, before it's edited
+    fn checkpoint(&mut self) {
+        self.undo.push_back(self.snapshot());
+        if self.undo.len() > UNDO_LIMIT {
+            self.undo.pop_front();
+        }
+        self.redo.clear();
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            items: self.items.clone(),
+            total_time: self.total_time,
+            marked: self.marked.clone(),
+            selected: self.state.selected(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.items = snapshot.items;
+        self.total_time = snapshot.total_time;
+        self.marked = snapshot.marked;
+        match snapshot.selected.filter(|&i| i < self.items.len()) {
+            Some(i) => self.select(i),
+            None => {
+                self.curr = 0;
+                self.unselect();
             }
+        }
+        self.drawn = false;
+    }
+
+    /// Take back the last edit. False if there's nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let Some(snapshot) = self.undo.pop_back() else {
+            return false;
+        };
+        self.redo.push(self.snapshot());
+        self.restore(snapshot);
+        true
+    }
+
+    /// Make the last undone edit again. False if there's nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        let Some(snapshot) = self.redo.pop() else {
+            return false;
+        };
+        self.undo.push_back(self.snapshot());
+        self.restore(snapshot);
+        true
+    }
+
+    /// Mark the selected song, or unmark it if it's marked.
+    pub fn toggle_mark(&mut self) {
+        if let Some(i) = self.state.selected().filter(|&i| i < self.items.len()) {
+            if !self.marked.remove(&i) {
+                self.marked.insert(i);
+            }
+        }
+    }
+
+    pub fn clear_marks(&mut self) {
+        self.marked.clear();
+    }
+
+    pub fn has_marks(&self) -> bool {
+        !self.marked.is_empty()
+    }
+
+    pub fn is_marked(&self, i: usize) -> bool {
+        self.marked.contains(&i)
+    }
+
+    /// The marked songs in queue order.
+    pub fn marked(&self) -> Vec<PathBuf> {
+        self.marked.iter().map(|&i| self.items[i].clone()).collect()
+    }
+
+    // the marked songs, or the selected one when none are marked
+    fn targets(&self) -> Vec<usize> {
+        if self.marked.is_empty() {
+            self.state
+                .selected()
+                .filter(|&i| i < self.items.len())
+                .into_iter()
+                .collect()
         } else {
+            self.marked.iter().copied().collect()
+        }
+    }
+
+    // marks follow their songs as the queue changes, `f` maps the old index of a song to its new
+    // one, None when it left the queue
+    fn remap_marks(&mut self, f: impl Fn(usize) -> Option<usize>) {
+        self.marked = self.marked.iter().filter_map(|&i| f(i)).collect();
+    }
+
+    /// Move the marked songs, or the selected one, one place up. A song only moves past one that
+    /// isn't moving.
+    pub fn move_up(&mut self) {
+        let mut moving = self.moving();
+        let mut order: Vec<usize> = (0..self.items.len()).collect();
+        for i in 1..order.len() {
+            if moving[i] && !moving[i - 1] {
+                order.swap(i - 1, i);
+                moving.swap(i - 1, i);
+            }
+        }
+        self.reorder(order);
+    }
+
+    pub fn move_down(&mut self) {
+        let mut moving = self.moving();
+        let mut order: Vec<usize> = (0..self.items.len()).collect();
+        for i in (0..order.len().saturating_sub(1)).rev() {
+            if moving[i] && !moving[i + 1] {
+                order.swap(i, i + 1);
+                moving.swap(i, i + 1);
+            }
+        }
+        self.reorder(order);
+    }
+
+    /// Move the marked songs, or the selected one, to the front so they play next. When
+    /// shuffling, the first of them is sure to play next.
+    pub fn move_to_top(&mut self) {
+        let moving = self.moving();
+        let (mut order, rest): (Vec<usize>, Vec<usize>) =
+            (0..self.items.len()).partition(|&i| moving[i]);
+        order.extend(rest);
+        self.reorder(order);
+    }
+
+    pub fn move_to_bottom(&mut self) {
+        let moving = self.moving();
+        let (mut order, rest): (Vec<usize>, Vec<usize>) =
+            (0..self.items.len()).partition(|&i| !moving[i]);
+        order.extend(rest);
+        self.reorder(order);
+    }
+
+    // for every song, whether it's one of the targets
+    fn moving(&self) -> Vec<bool> {
+        let mut moving = vec![false; self.items.len()];
+        for i in self.targets() {
+            moving[i] = true;
+        }
+        moving
+    }
+
+    // put the songs in a new order, `order` being the old index of every song in its new place.
+    // the selection and marks follow their songs
+    fn reorder(&mut self, order: Vec<usize>) {
+        if order.iter().enumerate().all(|(new, &old)| new == old) {
+            return;
+        }
+        self.checkpoint();
+
+        let mut new_index = vec![0; order.len()];
+        for (new, &old) in order.iter().enumerate() {
+            new_index[old] = new;
+        }
+
+        // a song put at the front was picked by hand, one moved away from it was drawn
+        if let Some(&front) = order.first().filter(|&&front| front != 0) {
+            self.drawn = self.targets().contains(&front);
+        }
+
+        self.items = order.iter().map(|&old| self.items[old].clone()).collect();
+        self.remap_marks(|i| Some(new_index[i]));
+        if self.state.selected().is_some() && self.curr < new_index.len() {
+            self.select(new_index[self.curr]);
+        }
+    }
+
+    /// Empty the queue, leaving the repeat and shuffle modes as they are.
+    pub fn clear(&mut self) {
+        self.checkpoint();
+        self.items.clear();
+        self.total_time = 0;
+        self.curr = 0;
+        self.drawn = false;
+        self.marked.clear();
+        self.unselect();
+    }
+
+    /// Put `items` in front of the queue, in the order given, so they play after the current
+    /// song. When shuffling, only the first of them is sure to play next.
+    pub fn add_next(&mut self, items: Vec<PathBuf>) {
+        if items.is_empty() {
+            return;
+        }
+
+        self.checkpoint();
+        let count = items.len();
+        for item in items.into_iter().rev() {
             self.total_time += self.item_length(&item);
-            self.items.push_back(item);
+            self.items.push_front(item);
+        }
+
+        // keep the same song selected
+        if self.state.selected().is_some() {
+            self.select(self.curr + count);
         }
+        self.remap_marks(|i| Some(i + count));
+        self.drawn = true;
     }
 
-    // remove item from items vector
-    pub fn remove(&mut self) {
+    /// Put a song that already played back in front of the queue. Under repeat all it went
+    /// round to the back when it started, so that copy moves instead of a second one being added.
+    pub fn add_again(&mut self, item: PathBuf) {
+        if self.repeat != RepeatMode::All || self.items.back() != Some(&item) {
+            self.add_next(vec![item]);
+            return;
+        }
+
+        self.checkpoint();
+        self.items.rotate_right(1);
+
+        let last = self.items.len() - 1;
+        let moved = |i: usize| if i == last { 0 } else { i + 1 };
+        if self.state.selected().is_some() {
+            self.select(moved(self.curr));
+        }
+        self.remap_marks(|i| Some(moved(i)));
+        self.drawn = true;
+    }
+
+    /// Take every copy of `path` out of the queue, without it being undoable. For songs that
+    /// can't be played, which would come back round again when repeating.
+    pub fn forget(&mut self, path: &Path) {
+        let found: Vec<usize> = (0..self.items.len())
+            .filter(|&i| self.items[i] == path)
+            .collect();
+        let Some(&first) = found.first() else {
+            return;
+        };
+
+        if first == 0 {
+            self.drawn = false;
+        }
+        for &i in found.iter().rev() {
+            self.decrement_total_time(i);
+            self.items.remove(i);
+        }
+        self.remap_marks(|i| {
+            let before = found.iter().filter(|&&f| f < i).count();
+            (!found.contains(&i)).then_some(i - before)
+        });
+
         if self.items.is_empty() {
-            // top of queue
-        } else if self.items.len() == 1 {
-            self.decrement_total_time();
-            self.items.remove(self.curr);
             self.unselect();
-        // if at bottom of queue, remove item and select item above above
-        } else if self.state.selected().unwrap() >= (self.items.len() - 1) {
-            self.decrement_total_time();
-            self.items.remove(self.curr);
-            self.curr -= 1;
-            self.state.select(Some(self.curr));
-        // else delete item
-        } else if !self.items.is_empty() {
-            self.decrement_total_time();
-            self.items.remove(self.curr);
+        } else if self.state.selected().is_some() {
+            let before = found.iter().filter(|&&i| i < self.curr).count();
+            self.select((self.curr - before).min(self.items.len() - 1));
+        }
+    }
+
+    /// Remove the marked songs, or the selected one. The selection stays in place.
+    pub fn remove(&mut self) {
+        let targets = self.targets();
+        let Some(&first) = targets.first() else {
+            return;
         };
+
+        self.checkpoint();
+
+        // the song drawn to play next is going, draw another
+        if first == 0 {
+            self.drawn = false;
+        }
+
+        for &i in targets.iter().rev() {
+            self.decrement_total_time(i);
+            self.items.remove(i);
+        }
+        self.marked.clear();
+
+        if self.items.is_empty() {
+            self.unselect();
+        } else if self.state.selected().is_some() {
+            let before = targets.iter().filter(|&&i| i < self.curr).count();
+            self.select((self.curr - before).min(self.items.len() - 1));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the files don't exist, so they have no tags and count as albums by folder
+    fn queue(paths: &[&str]) -> Queue {
+        let mut queue = Queue::with_items();
+        queue.set_seed(7);
+        for path in paths {
+            queue.add(PathBuf::from(path));
+        }
+        queue
+    }
+
+    fn items(queue: &Queue) -> Vec<&str> {
+        queue.items().iter().map(|p| p.to_str().unwrap()).collect()
+    }
+
+    fn play_round(queue: &mut Queue) -> Vec<PathBuf> {
+        (0..queue.length()).map(|_| queue.pop()).collect()
+    }
+
+    const SONGS: [&str; 6] = ["/a/1", "/a/2", "/a/3", "/b/1", "/b/2", "/c/1"];
+
+    #[test]
+    fn shuffled_songs_play_once_each() {
+        let mut queue = queue(&SONGS);
+        queue.set_shuffle_mode(ShuffleMode::Songs);
+
+        let mut played = play_round(&mut queue);
+        assert_ne!(played, SONGS.map(PathBuf::from));
+        played.sort();
+        assert_eq!(played, SONGS.map(PathBuf::from));
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn same_seed_shuffles_the_same() {
+        let mut a = queue(&SONGS);
+        let mut b = queue(&SONGS);
+        a.set_shuffle_mode(ShuffleMode::Songs);
+        b.set_shuffle_mode(ShuffleMode::Songs);
+        assert_eq!(play_round(&mut a), play_round(&mut b));
+    }
+
+    #[test]
+    fn shuffled_albums_play_through_in_order() {
+        let mut queue = queue(&SONGS);
+        queue.set_shuffle_mode(ShuffleMode::Albums);
+
+        let played = play_round(&mut queue);
+        let mut albums: Vec<&Path> = Vec::new();
+        for song in &played {
+            let album = song.parent().unwrap();
+            if albums.last() != Some(&album) {
+                assert!(!albums.contains(&album), "album split up in {played:?}");
+                albums.push(album);
+            }
+        }
+        assert_eq!(albums.len(), 3);
+
+        let first_album: Vec<&PathBuf> = played
+            .iter()
+            .filter(|song| song.starts_with("/a"))
+            .collect();
+        assert_eq!(first_album, ["/a/1", "/a/2", "/a/3"].map(Path::new));
+    }
+
+    #[test]
+    fn repeated_shuffle_starts_a_new_round_once_everything_played() {
+        let mut queue = queue(&SONGS);
+        queue.set_repeat(RepeatMode::All);
+        queue.set_shuffle_mode(ShuffleMode::Songs);
+
+        for _ in 0..3 {
+            let mut played = play_round(&mut queue);
+            played.sort();
+            assert_eq!(played, SONGS.map(PathBuf::from));
+        }
+    }
+
+    #[test]
+    fn drawing_keeps_the_selection_on_its_song() {
+        for (i, song) in SONGS.iter().enumerate() {
+            let mut queue = queue(&SONGS);
+            queue.set_shuffle_mode(ShuffleMode::Songs);
+            queue.select(i);
+
+            queue.upcoming();
+            assert_eq!(queue.item().unwrap(), Path::new(song));
+            assert_eq!(items(&queue)[queue.state().selected().unwrap()], *song);
+        }
+    }
+
+    // what undo and redo bring back
+    fn contents(queue: &Queue) -> (Vec<PathBuf>, Vec<usize>, Option<usize>) {
+        let marked = (0..queue.length())
+            .filter(|&i| queue.is_marked(i))
+            .collect();
+        (
+            queue.items().iter().cloned().collect(),
+            marked,
+            queue.state().selected(),
+        )
+    }
+
+    // `edit` changes the queue, undo takes it back and redo makes it again
+    fn assert_inverse(mut queue: Queue, edit: impl FnOnce(&mut Queue)) {
+        let before = contents(&queue);
+        edit(&mut queue);
+        let after = contents(&queue);
+        assert_ne!(before, after, "the edit didn't change anything");
+
+        assert!(queue.undo());
+        assert_eq!(contents(&queue), before);
+        assert!(queue.redo());
+        assert_eq!(contents(&queue), after);
+        assert!(!queue.redo());
+    }
+
+    fn selected(i: usize) -> Queue {
+        let mut queue = queue(&SONGS);
+        queue.select(i);
+        queue
+    }
+
+    fn marked(marks: &[usize]) -> Queue {
+        let mut queue = queue(&SONGS);
+        for &i in marks {
+            queue.select(i);
+            queue.toggle_mark();
+        }
+        queue.select(0);
+        queue
+    }
+
+    #[test]
+    fn undo_extend() {
+        assert_inverse(queue(&SONGS), |queue| {
+            queue.extend(vec![PathBuf::from("/d/1"), PathBuf::from("/d/2")])
+        });
+    }
+
+    #[test]
+    fn undo_remove() {
+        assert_inverse(selected(2), Queue::remove);
+        assert_inverse(marked(&[0, 3, 5]), Queue::remove);
+    }
+
+    #[test]
+    fn undo_moves() {
+        for edit in [
+            Queue::move_up,
+            Queue::move_down,
+            Queue::move_to_top,
+            Queue::move_to_bottom,
+        ] {
+            assert_inverse(selected(2), edit);
+            assert_inverse(marked(&[1, 4]), edit);
+        }
+    }
+
+    #[test]
+    fn undo_clear() {
+        assert_inverse(marked(&[1]), Queue::clear);
+    }
+
+    #[test]
+    fn undo_add_next() {
+        assert_inverse(selected(3), |queue| {
+            queue.add_next(vec![PathBuf::from("/d/1"), PathBuf::from("/d/2")])
+        });
+    }
+
+    #[test]
+    fn undo_shuffle() {
+        assert_inverse(marked(&[2]), Queue::shuffle);
+    }
+
+    #[test]
+    fn undo_keeps_only_the_latest_edits() {
+        let mut queue = queue(&[]);
+        for i in 0..UNDO_LIMIT + 10 {
+            queue.extend(vec![PathBuf::from(format!("/song/{i}"))]);
+        }
+
+        for _ in 0..UNDO_LIMIT {
+            assert!(queue.undo());
+        }
+        assert!(!queue.undo());
+        assert_eq!(queue.length(), 10);
+    }
+
+    #[test]
+    fn undo_leaves_out_songs_played_since() {
+        let mut queue = queue(&["/a/1", "/a/2", "/a/3"]);
+        queue.extend(vec![PathBuf::from("/b/1")]);
+        assert_eq!(queue.pop(), Path::new("/a/1"));
+
+        assert!(queue.undo());
+        assert_eq!(items(&queue), ["/a/2", "/a/3"]);
+        assert!(queue.redo());
+        assert_eq!(items(&queue), ["/a/2", "/a/3", "/b/1"]);
+    }
+
+    #[test]
+    fn undo_keeps_songs_played_since_at_the_back_when_repeating() {
+        let mut queue = queue(&["/a/1", "/a/2", "/a/3"]);
+        queue.set_repeat(RepeatMode::All);
+        queue.extend(vec![PathBuf::from("/b/1")]);
+        assert_eq!(queue.pop(), Path::new("/a/1"));
+
+        assert!(queue.undo());
+        assert_eq!(items(&queue), ["/a/2", "/a/3", "/a/1"]);
+        assert!(queue.redo());
+        assert_eq!(items(&queue), ["/a/2", "/a/3", "/b/1", "/a/1"]);
+    }
+
+    #[test]
+    fn forgotten_songs_dont_come_back_round() {
+        let mut queue = queue(&["/a/1", "/a/2", "/a/1", "/a/3"]);
+        queue.set_repeat(RepeatMode::All);
+        assert_eq!(queue.pop(), Path::new("/a/1"));
+        assert_eq!(items(&queue), ["/a/2", "/a/1", "/a/3", "/a/1"]);
+        queue.select(2);
+        queue.toggle_mark();
+
+        queue.forget(Path::new("/a/1"));
+        assert_eq!(items(&queue), ["/a/2", "/a/3"]);
+        assert_eq!(queue.item().unwrap(), Path::new("/a/3"));
+        assert_eq!(queue.marked(), [Path::new("/a/3")]);
+    }
+
+    #[test]
+    fn songs_added_again_under_repeat_all_arent_doubled() {
+        let mut queue = queue(&["/a/1", "/a/2", "/a/3"]);
+        queue.set_repeat(RepeatMode::All);
+        assert_eq!(queue.pop(), Path::new("/a/1"));
+        queue.select(2);
+        queue.toggle_mark();
+
+        queue.add_again(PathBuf::from("/a/1"));
+        assert_eq!(items(&queue), ["/a/1", "/a/2", "/a/3"]);
+        assert_eq!(queue.item().unwrap(), Path::new("/a/1"));
+        assert_eq!(queue.marked(), [Path::new("/a/1")]);
+
+        // without repeat the song is gone from the queue, so it comes back as a new copy
+        queue.set_repeat(RepeatMode::Off);
+        assert_eq!(queue.pop(), Path::new("/a/1"));
+        queue.add_again(PathBuf::from("/a/1"));
+        assert_eq!(items(&queue), ["/a/1", "/a/2", "/a/3"]);
     }
 }
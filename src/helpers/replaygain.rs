@@ -0,0 +1,64 @@
+use lofty::{ItemKey, Tag};
+
+/// Which ReplayGain values are used to level songs.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ReplayGainMode {
+    #[default]
+    Off,
+    /// Every song at the same loudness.
+    Track,
+    /// Albums at the same loudness, keeping the differences between their songs.
+    Album,
+}
+
+impl ReplayGainMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_ref() {
+            "off" => Some(Self::Off),
+            "track" => Some(Self::Track),
+            "album" => Some(Self::Album),
+            _ => None,
+        }
+    }
+}
+
+/// Linear gain to play a song tagged with `tag` at, `preamp` being extra decibels on top of
+/// the tagged gain. Limited so the tagged peak doesn't clip, songs without tags play as is.
+pub fn gain(tag: &Tag, mode: ReplayGainMode, preamp: f32) -> f32 {
+    let track = || {
+        let db = read(tag, &ItemKey::ReplayGainTrackGain)?;
+        Some((db, read(tag, &ItemKey::ReplayGainTrackPeak)))
+    };
+    let album = || {
+        let db = read(tag, &ItemKey::ReplayGainAlbumGain)?;
+        Some((db, read(tag, &ItemKey::ReplayGainAlbumPeak)))
+    };
+
+    // album mode falls back to the track values for songs that were scanned on their own
+    let tagged = match mode {
+        ReplayGainMode::Off => None,
+        ReplayGainMode::Track => track(),
+        ReplayGainMode::Album => album().or_else(track),
+    };
+
+    match tagged {
+        Some((db, peak)) => {
+            let gain = 10f32.powf((db + preamp) / 20.);
+            match peak {
+                Some(peak) if peak > 0. => gain.min(1. / peak),
+                _ => gain,
+            }
+        }
+        None => 1.,
+    }
+}
+
+// tagged values look like "-6.20 dB" for gains and "0.988312" for peaks
+fn read(tag: &Tag, key: &ItemKey) -> Option<f32> {
+    let value = tag.get_string(key)?.trim();
+    let value = value
+        .strip_suffix("dB")
+        .or_else(|| value.strip_suffix("db"))
+        .unwrap_or(value);
+    value.trim().parse().ok()
+}
@@ -0,0 +1,115 @@
+/// Incremental search through the items of a list, narrowing down the matches as the query is
+/// typed.
+pub struct Search {
+    query: String,
+    // selection when the search started, restored if it's cancelled
+    previous: Option<usize>,
+    // indices of the items matching `query`
+    matches: Vec<usize>,
+    current: usize,
+}
+
+impl Search {
+    pub fn new(previous: Option<usize>, items: &[String]) -> Self {
+        Self {
+            query: String::new(),
+            previous,
+            matches: (0..items.len()).collect(),
+            current: 0,
+        }
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn previous(&self) -> Option<usize> {
+        self.previous
+    }
+
+    pub fn push(&mut self, c: char, items: &[String]) {
+        self.query.push(c);
+        self.update(items);
+    }
+
+    pub fn pop(&mut self, items: &[String]) {
+        self.query.pop();
+        self.update(items);
+    }
+
+    /// Indices of the items matching the query, in list order.
+    pub fn matches(&self) -> &[usize] {
+        &self.matches
+    }
+
+    /// Position of the highlighted match within `matches`.
+    pub fn current(&self) -> Option<usize> {
+        (!self.matches.is_empty()).then_some(self.current)
+    }
+
+    /// Index of the highlighted item.
+    pub fn selected(&self) -> Option<usize> {
+        self.matches.get(self.current).copied()
+    }
+
+    pub fn next_match(&mut self) {
+        if !self.matches.is_empty() {
+            self.current = (self.current + 1) % self.matches.len();
+        }
+    }
+
+    pub fn previous_match(&mut self) {
+        if !self.matches.is_empty() {
+            self.current = (self.current + self.matches.len() - 1) % self.matches.len();
+        }
+    }
+
+    /// Match the query against `items` again after the list changed underneath the search,
+    /// keeping the highlight where it was as far as the matches allow.
+    pub fn refresh(&mut self, items: &[String]) {
+        self.matches = self.filter(items);
+        self.current = self.current.min(self.matches.len().saturating_sub(1));
+        self.previous = self.previous.filter(|&i| i < items.len());
+    }
+
+    fn update(&mut self, items: &[String]) {
+        self.matches = self.filter(items);
+        self.current = 0;
+    }
+
+    fn filter(&self, items: &[String]) -> Vec<usize> {
+        (0..items.len())
+            .filter(|&i| is_match(&self.query, &items[i]))
+            .collect()
+    }
+}
+
+/// Whether `item` contains `query`, ignoring case.
+pub fn is_match(query: &str, item: &str) -> bool {
+    item.to_lowercase().contains(&query.to_lowercase())
+}
+
+/// The first item after `from` matching `query`, wrapping around the end of the list. Searches
+/// backwards when `forward` is false.
+pub fn find_next(
+    query: &str,
+    items: &[String],
+    from: Option<usize>,
+    forward: bool,
+) -> Option<usize> {
+    let len = items.len();
+    if len == 0 {
+        return None;
+    }
+    let start = from.unwrap_or(if forward { len - 1 } else { 0 });
+
+    (1..=len)
+        .map(|step| {
+            if forward {
+                (start + step) % len
+            } else {
+                (start + len - step) % len
+            }
+        })
+        .find(|&i| is_match(query, &items[i]))
+}
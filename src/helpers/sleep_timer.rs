@@ -0,0 +1,53 @@
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+/// How long before the timer goes off the volume starts fading out.
+pub const FADE_OUT: Duration = Duration::from_secs(60);
+
+// the timer lengths to cycle through, in minutes
+const PRESETS: [u64; 3] = [15, 30, 60];
+
+/// When playback stops by itself.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SleepTimer {
+    #[default]
+    Off,
+    /// After a set number of minutes, ending at `end`.
+    After { minutes: u64, end: Instant },
+    /// Once the queue has played out.
+    EndOfQueue,
+}
+
+impl SleepTimer {
+    /// Get the next timer in the cycle, counting from now. The end of the queue is left out when
+    /// the queue doesn't end, as it doesn't while repeating.
+    pub fn next(&self, queue_ends: bool) -> Self {
+        let minutes = match self {
+            Self::Off => Some(PRESETS[0]),
+            Self::After { minutes, .. } => PRESETS.iter().copied().find(|preset| preset > minutes),
+            // Wrap around to the first timer.
+            Self::EndOfQueue => return Self::Off,
+        };
+
+        match minutes {
+            Some(minutes) => Self::After {
+                minutes,
+                end: Instant::now() + Duration::from_secs(minutes * 60),
+            },
+            None if queue_ends => Self::EndOfQueue,
+            None => Self::Off,
+        }
+    }
+}
+
+impl fmt::Display for SleepTimer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Off => write!(f, "Off"),
+            Self::After { minutes, .. } => write!(f, "{minutes} Min"),
+            Self::EndOfQueue => write!(f, "End Of Queue"),
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::{collections::BTreeSet, path::Path};
 use tui::widgets::ListState;
 
 // TODO encapsulation
@@ -6,6 +6,8 @@ pub struct StatefulList<T> {
     state: ListState,
     items: Vec<T>,
     curr: usize,
+    // indices of the items marked for adding together
+    marked: BTreeSet<usize>,
 }
 
 impl<T> StatefulList<T> {
@@ -14,6 +16,7 @@ impl<T> StatefulList<T> {
             state: ListState::default(),
             items,
             curr: 0,
+            marked: BTreeSet::new(),
         }
     }
 
@@ -82,10 +85,37 @@ impl<T> StatefulList<T> {
         self.curr = i;
         self.state.select(Some(i));
     }
+
+    // mark the selected item, or unmark it if it's marked
+    pub fn toggle_mark(&mut self) {
+        if self.state.selected().is_some()
+            && self.curr < self.items.len()
+            && !self.marked.remove(&self.curr)
+        {
+            self.marked.insert(self.curr);
+        }
+    }
+
+    pub fn clear_marks(&mut self) {
+        self.marked.clear();
+    }
+
+    pub fn has_marks(&self) -> bool {
+        !self.marked.is_empty()
+    }
+
+    pub fn is_marked(&self, i: usize) -> bool {
+        self.marked.contains(&i)
+    }
+
+    // marked items in list order
+    pub fn marked(&self) -> Vec<&T> {
+        self.marked.iter().map(|&i| &self.items[i]).collect()
+    }
 }
 
 impl<T: ToString> StatefulList<T> {
-    pub fn find_by_path(&self, s: &PathBuf) -> usize {
+    pub fn find_by_path(&self, s: &Path) -> usize {
         let mut i = 0;
 
         for n in 0 .. self.items.len() {
@@ -97,7 +127,7 @@ impl<T: ToString> StatefulList<T> {
 
         i
     }
-    pub fn select_by_path(&mut self, s: &PathBuf) {
+    pub fn select_by_path(&mut self, s: &Path) {
         self.select(self.find_by_path(s));
     }
 }
@@ -3,35 +3,15 @@ use tui::widgets::TableState;
 pub struct StatefulTable<'a> {
     pub header: Vec<&'a str>,
     pub state: TableState,
-    pub items: Vec<Vec<&'a str>>,
-}
-
-impl<'a> Default for StatefulTable<'a> {
-    fn default() -> Self {
-        Self::new()
-    }
+    pub items: Vec<Vec<String>>,
 }
 
 impl<'a> StatefulTable<'a> {
-    pub fn new() -> Self {
+    pub fn with_items(header: Vec<&'a str>, items: Vec<Vec<String>>) -> Self {
         Self {
-            header: vec!["Keys", "Commands"],
+            header,
             state: TableState::default(),
-            items: vec![
-                vec!["Q", "Quit"],
-                vec!["P", "Play / Pause"],
-                vec!["G", "Skip Song"],
-                vec!["A", "Add To Queue"],
-                vec!["R", "Remove From Queue"],
-                vec!["Enter", "Enter Directory"],
-                vec!["Backspace", "Previous Directory"],
-                vec!["Down", "Next Item"],
-                vec!["Up", "Previous Item"],
-                vec!["Right / Left", "Enter Queue / Browser"],
-                vec!["Tab", "Change Tabs"],
-                vec!["+", "Volume Up"],
-                vec!["-", "Volume Down"],
-            ],
+            items,
         }
     }
 
@@ -39,7 +19,7 @@ impl<'a> StatefulTable<'a> {
     //     self.header
     // }
 
-    // pub fn items(&self) -> Vec<Vec<&'a str>> {
+    // pub fn items(&self) -> Vec<Vec<String>> {
     //     self.items
     // }
 
@@ -0,0 +1,671 @@
+use std::{collections::HashMap, fmt};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::app::InputMode;
+
+/// Everything a key can be bound to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Quit,
+    PlayPause,
+    Skip,
+    PlayPrevious,
+    StopAfterCurrent,
+    CycleSleepTimer,
+    SeekBackward,
+    SeekForward,
+    /// Jump to this many tenths of the current song.
+    SeekTo(u8),
+    CycleAbLoop,
+    CycleRepeat,
+    CycleShuffle,
+    ShuffleQueue,
+    VolumeUp,
+    VolumeDown,
+    NextTab,
+    NextItem,
+    PreviousItem,
+    /// Enter a directory or play a song.
+    Select,
+    ParentDirectory,
+    AddToQueue,
+    PlayNext,
+    ToggleMark,
+    ClearMarks,
+    RemoveFromQueue,
+    MoveUp,
+    MoveDown,
+    MoveToTop,
+    MoveToBottom,
+    Undo,
+    Redo,
+    FocusQueue,
+    FocusBrowser,
+    CycleGrouping,
+    Search,
+    NextMatch,
+    PreviousMatch,
+    Find,
+    SavePlaylist,
+    RenamePlaylist,
+    DeletePlaylist,
+}
+
+impl Action {
+    /// Every action, in the order they're listed to the user.
+    pub fn all() -> Vec<Action> {
+        let mut actions = vec![
+            Action::Quit,
+            Action::PlayPause,
+            Action::Skip,
+            Action::PlayPrevious,
+            Action::StopAfterCurrent,
+            Action::CycleSleepTimer,
+            Action::SeekBackward,
+            Action::SeekForward,
+        ];
+        actions.extend((0..10).map(Action::SeekTo));
+        actions.extend([
+            Action::CycleAbLoop,
+            Action::CycleRepeat,
+            Action::CycleShuffle,
+            Action::ShuffleQueue,
+            Action::VolumeUp,
+            Action::VolumeDown,
+            Action::NextTab,
+            Action::NextItem,
+            Action::PreviousItem,
+            Action::Select,
+            Action::ParentDirectory,
+            Action::AddToQueue,
+            Action::PlayNext,
+            Action::ToggleMark,
+            Action::ClearMarks,
+            Action::RemoveFromQueue,
+            Action::MoveUp,
+            Action::MoveDown,
+            Action::MoveToTop,
+            Action::MoveToBottom,
+            Action::Undo,
+            Action::Redo,
+            Action::FocusQueue,
+            Action::FocusBrowser,
+            Action::CycleGrouping,
+            Action::Search,
+            Action::NextMatch,
+            Action::PreviousMatch,
+            Action::Find,
+            Action::SavePlaylist,
+            Action::RenamePlaylist,
+            Action::DeletePlaylist,
+        ]);
+        actions
+    }
+
+    /// Name used for the action in the `[keys]` table of config.toml.
+    pub fn name(&self) -> String {
+        match self {
+            Action::Quit => "quit".into(),
+            Action::PlayPause => "play_pause".into(),
+            Action::Skip => "skip".into(),
+            Action::PlayPrevious => "play_previous".into(),
+            Action::StopAfterCurrent => "stop_after_current".into(),
+            Action::CycleSleepTimer => "cycle_sleep_timer".into(),
+            Action::SeekBackward => "seek_backward".into(),
+            Action::SeekForward => "seek_forward".into(),
+            Action::SeekTo(tenths) => format!("seek_to_{}_percent", *tenths as u32 * 10),
+            Action::CycleAbLoop => "cycle_ab_loop".into(),
+            Action::CycleRepeat => "cycle_repeat".into(),
+            Action::CycleShuffle => "cycle_shuffle".into(),
+            Action::ShuffleQueue => "shuffle_queue".into(),
+            Action::VolumeUp => "volume_up".into(),
+            Action::VolumeDown => "volume_down".into(),
+            Action::NextTab => "next_tab".into(),
+            Action::NextItem => "next_item".into(),
+            Action::PreviousItem => "previous_item".into(),
+            Action::Select => "select".into(),
+            Action::ParentDirectory => "parent_directory".into(),
+            Action::AddToQueue => "add_to_queue".into(),
+            Action::PlayNext => "play_next".into(),
+            Action::ToggleMark => "toggle_mark".into(),
+            Action::ClearMarks => "clear_marks".into(),
+            Action::RemoveFromQueue => "remove_from_queue".into(),
+            Action::MoveUp => "move_up".into(),
+            Action::MoveDown => "move_down".into(),
+            Action::MoveToTop => "move_to_top".into(),
+            Action::MoveToBottom => "move_to_bottom".into(),
+            Action::Undo => "undo".into(),
+            Action::Redo => "redo".into(),
+            Action::FocusQueue => "focus_queue".into(),
+            Action::FocusBrowser => "focus_browser".into(),
+            Action::CycleGrouping => "cycle_grouping".into(),
+            Action::Search => "search".into(),
+            Action::NextMatch => "next_match".into(),
+            Action::PreviousMatch => "previous_match".into(),
+            Action::Find => "find".into(),
+            Action::SavePlaylist => "save_playlist".into(),
+            Action::RenamePlaylist => "rename_playlist".into(),
+            Action::DeletePlaylist => "delete_playlist".into(),
+        }
+    }
+
+    /// Whether the action does anything while `mode` has focus.
+    pub fn is_active(&self, mode: InputMode) -> bool {
+        match self {
+            Action::Select => matches!(
+                mode,
+                InputMode::Browser
+                    | InputMode::Queue
+                    | InputMode::Playlists
+                    | InputMode::Library
+                    | InputMode::History
+            ),
+            Action::ParentDirectory => matches!(mode, InputMode::Browser | InputMode::Library),
+            Action::PlayNext => matches!(
+                mode,
+                InputMode::Browser | InputMode::Library | InputMode::History
+            ),
+            Action::AddToQueue => matches!(
+                mode,
+                InputMode::Browser | InputMode::Playlists | InputMode::Library | InputMode::History
+            ),
+            Action::FocusQueue => matches!(mode, InputMode::Browser | InputMode::Playlists),
+            Action::RemoveFromQueue
+            | Action::MoveUp
+            | Action::MoveDown
+            | Action::MoveToTop
+            | Action::MoveToBottom
+            | Action::FocusBrowser => matches!(mode, InputMode::Queue | InputMode::Playlists),
+            Action::RenamePlaylist | Action::DeletePlaylist => {
+                matches!(mode, InputMode::Playlists)
+            }
+            Action::CycleGrouping => matches!(mode, InputMode::Library),
+            Action::ToggleMark
+            | Action::ClearMarks
+            | Action::Search
+            | Action::NextMatch
+            | Action::PreviousMatch => {
+                matches!(mode, InputMode::Browser | InputMode::Queue)
+            }
+            _ => true,
+        }
+    }
+
+    /// What the action does while `mode` has focus, as shown in the Controls tab.
+    pub fn description(&self, mode: InputMode) -> String {
+        match self {
+            Action::Quit => "Quit".into(),
+            Action::PlayPause => "Play / Pause".into(),
+            Action::Skip => "Skip Song".into(),
+            Action::PlayPrevious => "Restart Song / Previous Song".into(),
+            Action::StopAfterCurrent => "Stop After Current Song".into(),
+            Action::CycleSleepTimer => "Sleep Timer 15 / 30 / 60 Min / End Of Queue".into(),
+            Action::SeekBackward => "Seek -5s".into(),
+            Action::SeekForward => "Seek +5s".into(),
+            Action::SeekTo(tenths) => format!("Seek To {}%", *tenths as u32 * 10),
+            Action::CycleAbLoop => "Set Loop Start / Set Loop End / Stop Looping".into(),
+            Action::CycleRepeat => "Cycle Repeat Mode".into(),
+            Action::CycleShuffle => "Cycle Shuffle Mode".into(),
+            Action::ShuffleQueue => "Shuffle Queue".into(),
+            Action::VolumeUp => "Volume Up".into(),
+            Action::VolumeDown => "Volume Down".into(),
+            Action::NextTab => "Change Tabs".into(),
+            Action::NextItem => "Next Item".into(),
+            Action::PreviousItem => "Previous Item".into(),
+            Action::Select => match mode {
+                InputMode::Browser => "Enter Directory / Play Song".into(),
+                InputMode::Library => "Open Artist / Album, Play Song".into(),
+                InputMode::Playlists => "Replace Queue With Playlist".into(),
+                _ => "Play Song".into(),
+            },
+            Action::ParentDirectory => match mode {
+                InputMode::Library => "Back To Artists / Albums".into(),
+                _ => "Previous Directory".into(),
+            },
+            Action::AddToQueue => match mode {
+                InputMode::Playlists => "Add Playlist / Song To Queue".into(),
+                _ => "Add To Queue".into(),
+            },
+            Action::PlayNext => "Play Next".into(),
+            Action::ToggleMark => "Mark / Unmark, Next Item".into(),
+            Action::ClearMarks => "Unmark All".into(),
+            Action::RemoveFromQueue => match mode {
+                InputMode::Playlists => "Remove Song From Playlist".into(),
+                _ => "Remove From Queue".into(),
+            },
+            Action::MoveUp => "Move Song Up".into(),
+            Action::MoveDown => "Move Song Down".into(),
+            Action::MoveToTop => match mode {
+                InputMode::Queue => "Move Song To Top, Play Next".into(),
+                _ => "Move Song To Top".into(),
+            },
+            Action::MoveToBottom => "Move Song To Bottom".into(),
+            Action::Undo => "Undo Queue Edit".into(),
+            Action::Redo => "Redo Queue Edit".into(),
+            Action::FocusQueue => match mode {
+                InputMode::Playlists => "Edit Playlist".into(),
+                _ => "Enter Queue".into(),
+            },
+            Action::FocusBrowser => match mode {
+                InputMode::Playlists => "Back To Playlists".into(),
+                _ => "Enter Browser".into(),
+            },
+            Action::CycleGrouping => "Group By Artist / Genre / Year".into(),
+            Action::Search => "Search".into(),
+            Action::NextMatch => "Next Match".into(),
+            Action::PreviousMatch => "Previous Match".into(),
+            Action::Find => "Find In Library".into(),
+            Action::SavePlaylist => "Save Queue As Playlist".into(),
+            Action::RenamePlaylist => "Rename Playlist".into(),
+            Action::DeletePlaylist => "Delete Playlist".into(),
+        }
+    }
+
+    fn default_keys(&self) -> &'static [&'static str] {
+        match self {
+            Action::Quit => &["q"],
+            Action::PlayPause => &["p", "space"],
+            Action::Skip => &["g"],
+            Action::PlayPrevious => &["b"],
+            Action::StopAfterCurrent => &["x"],
+            Action::CycleSleepTimer => &["z"],
+            Action::SeekBackward => &[","],
+            Action::SeekForward => &["."],
+            Action::SeekTo(tenths) => {
+                const DIGITS: [&str; 10] = ["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"];
+                std::slice::from_ref(&DIGITS[*tenths as usize])
+            }
+            Action::CycleAbLoop => &["L"],
+            Action::CycleRepeat => &["R"],
+            Action::CycleShuffle => &["s"],
+            Action::ShuffleQueue => &["S"],
+            Action::VolumeUp => &["+"],
+            Action::VolumeDown => &["-"],
+            Action::NextTab => &["tab"],
+            Action::NextItem => &["down", "j"],
+            Action::PreviousItem => &["up", "k"],
+            Action::Select => &["enter"],
+            Action::ParentDirectory => &["backspace"],
+            Action::AddToQueue => &["a"],
+            Action::PlayNext => &["A"],
+            Action::ToggleMark => &["v"],
+            Action::ClearMarks => &["V"],
+            Action::RemoveFromQueue => &["r"],
+            Action::MoveUp => &["K"],
+            Action::MoveDown => &["J"],
+            Action::MoveToTop => &["T"],
+            Action::MoveToBottom => &["B"],
+            Action::Undo => &["u"],
+            Action::Redo => &["ctrl+r"],
+            Action::FocusQueue => &["right", "l"],
+            Action::FocusBrowser => &["left", "h"],
+            Action::CycleGrouping => &["v"],
+            Action::Search => &["/"],
+            Action::NextMatch => &["n"],
+            Action::PreviousMatch => &["N"],
+            Action::Find => &["f"],
+            Action::SavePlaylist => &["w"],
+            Action::RenamePlaylist => &["c"],
+            Action::DeletePlaylist => &["d"],
+        }
+    }
+}
+
+/// A key together with the ctrl, alt and shift modifiers held down with it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyChord {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let mut modifiers =
+            modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        // shift is already part of upper case letters and back tab
+        if matches!(code, KeyCode::Char(_) | KeyCode::BackTab) {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+
+        Self { code, modifiers }
+    }
+
+    pub fn from_event(key: KeyEvent) -> Self {
+        Self::new(key.code, key.modifiers)
+    }
+
+    /// Parse chords like `q`, `R`, `space`, `ctrl+r` or `alt+enter`.
+    pub fn parse(chord: &str) -> Result<Self, String> {
+        // `+` is both a key and the separator
+        let (modifier_names, key) = match chord.strip_suffix("++") {
+            Some(modifier_names) => (modifier_names, "+"),
+            None => match chord.rsplit_once('+') {
+                Some((modifier_names, key)) if !key.is_empty() => (modifier_names, key),
+                _ => ("", chord),
+            },
+        };
+
+        let mut modifiers = KeyModifiers::NONE;
+        for name in modifier_names.split('+').filter(|name| !name.is_empty()) {
+            modifiers |= match name.to_ascii_lowercase().as_ref() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier \"{name}\" in \"{chord}\"")),
+            };
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+                KeyCode::Char(c.to_ascii_uppercase())
+            }
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_ascii_lowercase().as_ref() {
+                "space" => KeyCode::Char(' '),
+                "enter" => KeyCode::Enter,
+                "backspace" => KeyCode::Backspace,
+                "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "esc" | "escape" => KeyCode::Esc,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "delete" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                name => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n) if (1..=12).contains(&n) => KeyCode::F(n),
+                    _ => return Err(format!("unknown key \"{key}\" in \"{chord}\"")),
+                },
+            },
+        };
+
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "shift+")?;
+        }
+
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::Enter => write!(f, "enter"),
+            KeyCode::Backspace => write!(f, "backspace"),
+            KeyCode::Tab => write!(f, "tab"),
+            KeyCode::BackTab => write!(f, "shift+tab"),
+            KeyCode::Esc => write!(f, "esc"),
+            KeyCode::Up => write!(f, "up"),
+            KeyCode::Down => write!(f, "down"),
+            KeyCode::Left => write!(f, "left"),
+            KeyCode::Right => write!(f, "right"),
+            KeyCode::Home => write!(f, "home"),
+            KeyCode::End => write!(f, "end"),
+            KeyCode::PageUp => write!(f, "pageup"),
+            KeyCode::PageDown => write!(f, "pagedown"),
+            KeyCode::Delete => write!(f, "delete"),
+            KeyCode::Insert => write!(f, "insert"),
+            KeyCode::F(n) => write!(f, "f{n}"),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+/// Which keys trigger which actions.
+pub struct Keymap {
+    bindings: Vec<(Action, Vec<KeyChord>)>,
+}
+
+impl Keymap {
+    /// Default bindings, with the actions named in `overrides` bound to the listed chords instead.
+    pub fn new(overrides: &HashMap<String, Vec<String>>) -> Result<Self, String> {
+        let actions = Action::all();
+
+        if let Some(name) = overrides
+            .keys()
+            .find(|name| !actions.iter().any(|action| action.name() == **name))
+        {
+            return Err(format!(
+                "Unknown action \"{name}\" in [keys] of config.toml"
+            ));
+        }
+
+        let mut bindings = Vec::new();
+        for action in actions {
+            let chords = match overrides.get(&action.name()) {
+                Some(chords) => chords.iter().map(|chord| chord.as_str()).collect(),
+                None => action.default_keys().to_vec(),
+            };
+            let chords = chords
+                .into_iter()
+                .map(KeyChord::parse)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|err| {
+                    format!(
+                        "Invalid key for {} in [keys] of config.toml: {err}",
+                        action.name()
+                    )
+                })?;
+            bindings.push((action, chords));
+        }
+
+        let keymap = Self { bindings };
+        keymap.check_conflicts()?;
+        Ok(keymap)
+    }
+
+    // two actions can share a key as long as they're never active at the same time
+    fn check_conflicts(&self) -> Result<(), String> {
+        for (i, (action, chords)) in self.bindings.iter().enumerate() {
+            for (other, other_chords) in &self.bindings[i + 1..] {
+                let shared = chords.iter().find(|chord| other_chords.contains(chord));
+                let overlap = InputMode::ALL
+                    .iter()
+                    .any(|&mode| action.is_active(mode) && other.is_active(mode));
+
+                if let (Some(chord), true) = (shared, overlap) {
+                    return Err(format!(
+                        "Key \"{chord}\" is bound to both {} and {} in [keys] of config.toml",
+                        action.name(),
+                        other.name(),
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The action `key` triggers while `mode` has focus.
+    pub fn action(&self, mode: InputMode, key: KeyEvent) -> Option<Action> {
+        let chord = KeyChord::from_event(key);
+
+        self.bindings
+            .iter()
+            .find(|(action, chords)| action.is_active(mode) && chords.contains(&chord))
+            .map(|(action, _)| *action)
+    }
+
+    /// Rows of the Controls tab: pane, keys and what they do. Actions that work everywhere come
+    /// first, followed by those specific to the browser, the queue, the playlists, the library
+    /// and the history, and the keys used while typing a search or into the finder, answering a
+    /// prompt or naming a playlist.
+    pub fn controls(&self) -> Vec<Vec<String>> {
+        let global = |action: &Action| InputMode::ALL.iter().all(|&mode| action.is_active(mode));
+        let groups = [
+            ("Everywhere", None),
+            ("Browser", Some(InputMode::Browser)),
+            ("Queue", Some(InputMode::Queue)),
+            ("Playlists", Some(InputMode::Playlists)),
+            ("Library", Some(InputMode::Library)),
+            ("History", Some(InputMode::History)),
+        ];
+
+        let mut rows = Vec::new();
+        for (pane, mode) in groups {
+            let bindings = self.bindings.iter().filter(|(action, chords)| {
+                let in_group = match mode {
+                    Some(mode) => action.is_active(mode) && !global(action),
+                    None => global(action),
+                };
+                in_group && !chords.is_empty()
+            });
+
+            for (i, (action, chords)) in bindings.enumerate() {
+                let keys: Vec<String> = chords.iter().map(|chord| chord.to_string()).collect();
+                rows.push(vec![
+                    if i == 0 { pane.into() } else { String::new() },
+                    keys.join(" / "),
+                    action.description(mode.unwrap_or(InputMode::Browser)),
+                ]);
+            }
+        }
+
+        // typed text goes into the query, so these aren't configurable
+        let text_input = [
+            ("Search", "esc", "Cancel Search"),
+            ("", "enter", "Go To Match"),
+            ("", "down / up", "Next / Previous Match"),
+            ("Find", "esc", "Close Finder"),
+            ("", "enter", "Play Song"),
+            ("", "tab", "Add To Queue"),
+            ("", "down / up", "Next / Previous Result"),
+            ("Confirm", "y / enter", "Yes"),
+            ("", "n / esc", "No"),
+            ("Name Playlist", "enter", "Save"),
+            ("", "esc", "Cancel"),
+        ];
+        for (pane, keys, description) in text_input {
+            rows.push(vec![pane.into(), keys.into(), description.into()]);
+        }
+
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chord(code: KeyCode, modifiers: KeyModifiers) -> KeyChord {
+        KeyChord::new(code, modifiers)
+    }
+
+    fn keymap(overrides: &[(&str, &[&str])]) -> Result<Keymap, String> {
+        let overrides = overrides
+            .iter()
+            .map(|(action, chords)| {
+                let chords = chords.iter().map(|chord| chord.to_string()).collect();
+                (action.to_string(), chords)
+            })
+            .collect();
+        Keymap::new(&overrides)
+    }
+
+    #[test]
+    fn parses_chords() {
+        let none = KeyModifiers::NONE;
+        let cases = [
+            ("q", chord(KeyCode::Char('q'), none)),
+            ("R", chord(KeyCode::Char('R'), none)),
+            ("shift+r", chord(KeyCode::Char('R'), none)),
+            ("ctrl+r", chord(KeyCode::Char('r'), KeyModifiers::CONTROL)),
+            (
+                "Ctrl+Alt+Enter",
+                chord(KeyCode::Enter, KeyModifiers::CONTROL | KeyModifiers::ALT),
+            ),
+            ("+", chord(KeyCode::Char('+'), none)),
+            ("ctrl++", chord(KeyCode::Char('+'), KeyModifiers::CONTROL)),
+            ("space", chord(KeyCode::Char(' '), none)),
+            ("shift+tab", chord(KeyCode::BackTab, none)),
+            ("backtab", chord(KeyCode::BackTab, none)),
+            ("PageDown", chord(KeyCode::PageDown, none)),
+            ("f12", chord(KeyCode::F(12), none)),
+        ];
+
+        for (text, expected) in cases {
+            assert_eq!(KeyChord::parse(text), Ok(expected), "{text}");
+        }
+    }
+
+    #[test]
+    fn rejects_bad_chords() {
+        for text in ["", "qq", "hyper+q", "ctrl+", "f0", "f13", "enterr"] {
+            assert!(KeyChord::parse(text).is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn chords_display_as_they_parse() {
+        for text in [
+            "q",
+            "R",
+            "ctrl+r",
+            "ctrl+alt+enter",
+            "space",
+            "shift+tab",
+            "f5",
+        ] {
+            assert_eq!(KeyChord::parse(text).unwrap().to_string(), text);
+        }
+    }
+
+    #[test]
+    fn events_match_the_chords_they_were_typed_as() {
+        let event = KeyEvent::new(KeyCode::Char('R'), KeyModifiers::SHIFT);
+        assert_eq!(KeyChord::from_event(event), KeyChord::parse("R").unwrap());
+    }
+
+    #[test]
+    fn default_keys_dont_conflict() {
+        assert!(keymap(&[]).is_ok());
+    }
+
+    #[test]
+    fn keys_shared_in_one_pane_conflict() {
+        // both work everywhere
+        let err = keymap(&[("skip", &["q"])]).err().unwrap();
+        assert!(err.contains("quit") && err.contains("skip"), "{err}");
+        // both work in the queue
+        assert!(keymap(&[("remove_from_queue", &["/"])]).is_err());
+    }
+
+    #[test]
+    fn keys_can_be_shared_by_different_panes() {
+        // the library has no marks and the browser no groupings
+        let keymap = keymap(&[("cycle_grouping", &["m"]), ("toggle_mark", &["m"])]).unwrap();
+        let key = KeyEvent::new(KeyCode::Char('m'), KeyModifiers::NONE);
+
+        assert_eq!(
+            keymap.action(InputMode::Library, key),
+            Some(Action::CycleGrouping)
+        );
+        assert_eq!(
+            keymap.action(InputMode::Browser, key),
+            Some(Action::ToggleMark)
+        );
+        assert_eq!(keymap.action(InputMode::History, key), None);
+    }
+
+    #[test]
+    fn unknown_actions_and_keys_are_errors() {
+        assert!(keymap(&[("dance", &["d"])]).is_err());
+        assert!(keymap(&[("quit", &["ctrl+qq"])]).is_err());
+    }
+}
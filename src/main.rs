@@ -1,11 +1,12 @@
 mod app;
 mod config;
+mod keymap;
 mod state;
 
 use std::{error::Error, io, time::{Duration, Instant}};
 
 use crossterm::{
-    event::{self, DisableMouseCapture, Event, KeyCode},
+    event::{self, DisableMouseCapture, Event},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -14,17 +15,33 @@ use tui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Modifier, Style},
     text::{Span, Spans, Text},
-    widgets::{Block, BorderType, Borders, Cell, Gauge, List, ListItem, Row, Table, Tabs},
+    widgets::{
+        Block, BorderType, Borders, Cell, Clear, Gauge, List, ListItem, ListState, Paragraph, Row,
+        Table, Tabs,
+    },
     Frame, Terminal,
 };
 
 use app::{App, AppTab, InputMode};
 use config::Config;
+use keymap::{Action, Keymap};
 use kronos::gen_funcs;
+use kronos::music_handler::AbLoop;
+use kronos::sleep_timer::SleepTimer;
 use state::load_state;
 
 fn main() -> Result<(), Box<dyn Error>> {
     let state = load_state();
+    let cfg = Config::new();
+
+    // bad bindings are reported before the terminal is taken over
+    let keymap = match Keymap::new(cfg.keys()) {
+        Ok(keymap) => keymap,
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+    };
 
     // setup terminal
     enable_raw_mode()?;
@@ -35,11 +52,16 @@ fn main() -> Result<(), Box<dyn Error>> {
     let mut terminal = Terminal::new(backend)?;
 
     // create app and run it
-    let tick_rate = Duration::from_secs(1);
-    let app = App::new(state.last_visited_path);
-    let cfg = Config::new();
+    let tick_rate = Duration::from_millis(250);
+    let mut app = App::new(state.last_visited_path.clone(), &keymap);
+    app.music_handle.set_crossfade(cfg.crossfade());
+    app.music_handle
+        .set_replaygain(cfg.replaygain(), cfg.replaygain_preamp());
+    app.restore_state(state);
+    app.set_enqueue_limits(cfg.max_depth(), cfg.confirm_above());
+    app.library.scan(cfg.library_path());
 
-    let res = run_app(&mut terminal, app, cfg, tick_rate);
+    let res = run_app(&mut terminal, app, cfg, keymap, tick_rate);
 
     // restore terminal
     disable_raw_mode()?;
@@ -61,85 +83,195 @@ fn run_app<B: Backend>(
     terminal: &mut Terminal<B>,
     mut app: App,
     cfg: Config,
+    keymap: Keymap,
     tick_rate: Duration,
 ) -> io::Result<()> {
     let mut last_tick = Instant::now();
     loop {
+        app.auto_play();
+        app.record_history();
+        app.update_sleep_timer();
+        app.update_search();
+        app.library.update();
         terminal.draw(|f| ui(f, &mut app, &cfg))?;
 
         let timeout = tick_rate
             .checked_sub(last_tick.elapsed())
             .unwrap_or_else(|| Duration::from_secs(0));
         if crossterm::event::poll(timeout)? {
-            // different keys depending on which browser tab
             if let Event::Key(key) = event::read()? {
-                match app.input_mode() {
-                    InputMode::Browser => match key.code {
-                        KeyCode::Char('q') => break,
-                        KeyCode::Char('p') | KeyCode::Char(' ') => app.music_handle.play_pause(),
-                        KeyCode::Char('g') => app.music_handle.skip(),
-                        KeyCode::Char('a') => app.queue_items.add(app.selected_item()),
-                        KeyCode::Enter => app.evaluate(),
-                        KeyCode::Backspace => app.backpedal(),
-                        KeyCode::Down | KeyCode::Char('j') => app.browser_items.next(),
-                        KeyCode::Up | KeyCode::Char('k') => app.browser_items.previous(),
-                        KeyCode::Right | KeyCode::Char('l') => {
-                            app.browser_items.unselect();
-                            app.set_input_mode(InputMode::Queue);
-                            app.queue_items.next();
+                // the same key can do different things depending on which pane has focus
+                if app.notice().is_some() && app.input_mode() != InputMode::Confirm {
+                    app.dismiss_notice();
+                } else if app.input_mode() == InputMode::Search {
+                    app.search_input(key);
+                } else if app.input_mode() == InputMode::Finder {
+                    app.finder_input(key);
+                } else if app.input_mode() == InputMode::Confirm {
+                    app.confirm_input(key);
+                } else if app.input_mode() == InputMode::SavePlaylist {
+                    app.save_playlist_input(key);
+                } else if let Some(action) = keymap.action(app.input_mode(), key) {
+                    match action {
+                        Action::Quit => break,
+                        Action::PlayPause => app.play_pause(),
+                        Action::Skip => app.music_handle.skip(),
+                        Action::PlayPrevious => app.play_previous(),
+                        Action::StopAfterCurrent => app.toggle_stop_after_current(),
+                        Action::CycleSleepTimer => app.cycle_sleep_timer(),
+                        Action::SeekBackward => app.music_handle.seek(-5),
+                        Action::SeekForward => app.music_handle.seek(5),
+                        Action::SeekTo(tenths) => {
+                            let pos = app.music_handle.song_length() * tenths as u32 / 10;
+                            app.music_handle.seek_to(pos);
                         }
-                        KeyCode::Char('-') => app.music_handle.change_volume(-0.05),
-                        KeyCode::Char('+') => app.music_handle.change_volume(0.05),
-                        KeyCode::Tab => {
-                            app.next();
-                            match app.input_mode() {
-                                InputMode::Controls => app.set_input_mode(InputMode::Browser),
-                                _ => app.set_input_mode(InputMode::Controls),
-                            };
+                        Action::CycleAbLoop => app.music_handle.cycle_ab_loop(),
+                        Action::CycleRepeat => app.queue_items.cycle_repeat(),
+                        Action::CycleShuffle => app.queue_items.cycle_shuffle(),
+                        Action::ShuffleQueue => app.queue_items.shuffle(),
+                        Action::VolumeUp => app.music_handle.change_volume(0.05),
+                        Action::VolumeDown => app.music_handle.change_volume(-0.05),
+                        Action::NextTab => app.next(),
+                        Action::NextItem => match app.input_mode() {
+                            InputMode::Browser => app.browser_items.next(),
+                            InputMode::Queue => app.queue_items.next(),
+                            InputMode::Playlists => app.playlists.next(),
+                            InputMode::Library => app.library.next(),
+                            InputMode::History => app.history.next(),
+                            InputMode::Controls => app.control_table.next(),
+                            _ => {}
+                        },
+                        Action::PreviousItem => match app.input_mode() {
+                            InputMode::Browser => app.browser_items.previous(),
+                            InputMode::Queue => app.queue_items.previous(),
+                            InputMode::Playlists => app.playlists.previous(),
+                            InputMode::Library => app.library.previous(),
+                            InputMode::History => app.history.previous(),
+                            InputMode::Controls => app.control_table.previous(),
+                            _ => {}
+                        },
+                        Action::Select => match app.input_mode() {
+                            InputMode::Queue => {
+                                if let Some(i) = app.queue_items.item() {
+                                    app.play(i.clone());
+                                };
+                            }
+                            InputMode::Playlists => app.load_playlist(),
+                            InputMode::History => {
+                                if let Some(path) = app.history.selected() {
+                                    app.play(path.clone());
+                                }
+                            }
+                            InputMode::Library => {
+                                if let Some(path) = app.library.descend() {
+                                    app.play(path);
+                                }
+                            }
+                            _ => app.evaluate(),
+                        },
+                        Action::ParentDirectory => match app.input_mode() {
+                            InputMode::Library => app.library.ascend(),
+                            _ => app.backpedal(),
+                        },
+                        Action::AddToQueue => match app.input_mode() {
+                            InputMode::Playlists => {
+                                if !app.playlists.editing() {
+                                    app.append_playlist();
+                                } else if let Some(song) = app.playlists.selected_song() {
+                                    app.queue_items.extend(vec![song.clone()]);
+                                }
+                            }
+                            InputMode::Library => app.enqueue(app.library.selection()),
+                            InputMode::History => {
+                                if let Some(path) = app.history.selected() {
+                                    app.queue_items.extend(vec![path.clone()]);
+                                }
+                            }
+                            _ => app.add_selection(false),
+                        },
+                        Action::PlayNext => match app.input_mode() {
+                            InputMode::Library => app.enqueue_at(app.library.selection(), true),
+                            InputMode::History => {
+                                if let Some(path) = app.history.selected() {
+                                    app.queue_items.add_next(vec![path.clone()]);
+                                }
+                            }
+                            _ => app.add_selection(true),
+                        },
+                        Action::ToggleMark => match app.input_mode() {
+                            InputMode::Queue => {
+                                app.queue_items.toggle_mark();
+                                app.queue_items.next();
+                            }
+                            _ => {
+                                app.browser_items.toggle_mark();
+                                app.browser_items.next();
+                            }
+                        },
+                        Action::ClearMarks => match app.input_mode() {
+                            InputMode::Queue => app.queue_items.clear_marks(),
+                            _ => app.browser_items.clear_marks(),
+                        },
+                        Action::RemoveFromQueue => match app.input_mode() {
+                            InputMode::Playlists => app.remove_from_playlist(),
+                            _ => app.queue_items.remove(),
+                        },
+                        Action::MoveUp => match app.input_mode() {
+                            InputMode::Playlists => {
+                                let i = app.playlists.song_position();
+                                app.move_in_playlist(i.saturating_sub(1));
+                            }
+                            _ => app.queue_items.move_up(),
+                        },
+                        Action::MoveDown => match app.input_mode() {
+                            InputMode::Playlists => {
+                                let i = app.playlists.song_position();
+                                app.move_in_playlist(i + 1);
+                            }
+                            _ => app.queue_items.move_down(),
+                        },
+                        Action::MoveToTop => match app.input_mode() {
+                            InputMode::Playlists => app.move_in_playlist(0),
+                            _ => app.queue_items.move_to_top(),
+                        },
+                        Action::MoveToBottom => match app.input_mode() {
+                            InputMode::Playlists => {
+                                let last = app.playlists.songs().len().saturating_sub(1);
+                                app.move_in_playlist(last);
+                            }
+                            _ => app.queue_items.move_to_bottom(),
+                        },
+                        Action::Undo => {
+                            app.queue_items.undo();
                         }
-                        _ => {}
-                    },
-                    InputMode::Queue => match key.code {
-                        KeyCode::Char('q') => break,
-                        KeyCode::Char('p') => app.music_handle.play_pause(),
-                        KeyCode::Char('g') => app.music_handle.skip(),
-                        KeyCode::Enter => {
-                            if let Some(i) = app.queue_items.item() {
-                                app.music_handle.play(i.clone());
-                            };
+                        Action::Redo => {
+                            app.queue_items.redo();
                         }
-                        KeyCode::Down | KeyCode::Char('j') => app.queue_items.next(),
-                        KeyCode::Up | KeyCode::Char('k') => app.queue_items.previous(),
-                        KeyCode::Char('r') => app.queue_items.remove(),
-                        KeyCode::Left | KeyCode::Char('h') => {
-                            app.queue_items.unselect();
-                            app.set_input_mode(InputMode::Browser);
-                            app.browser_items.next();
-                        }
-                        KeyCode::Tab => {
-                            app.next();
-                            match app.input_mode() {
-                                InputMode::Controls => app.set_input_mode(InputMode::Browser),
-                                _ => app.set_input_mode(InputMode::Controls),
-                            };
-                        }
-                        _ => {}
-                    },
-                    InputMode::Controls => match key.code {
-                        KeyCode::Char('q') => break,
-                        KeyCode::Char('p') => app.music_handle.play_pause(),
-                        KeyCode::Char('g') => app.music_handle.skip(),
-                        KeyCode::Down | KeyCode::Char('j') => app.control_table.next(),
-                        KeyCode::Up | KeyCode::Char('k') => app.control_table.previous(),
-                        KeyCode::Tab => {
-                            app.next();
-                            match app.input_mode() {
-                                InputMode::Controls => app.set_input_mode(InputMode::Browser),
-                                _ => app.set_input_mode(InputMode::Controls),
-                            };
-                        }
-                        _ => {}
-                    },
+                        Action::FocusQueue => match app.input_mode() {
+                            InputMode::Playlists => app.playlists.edit(),
+                            _ => {
+                                app.browser_items.unselect();
+                                app.set_input_mode(InputMode::Queue);
+                                app.queue_items.next();
+                            }
+                        },
+                        Action::FocusBrowser => match app.input_mode() {
+                            InputMode::Playlists => app.playlists.stop_editing(),
+                            _ => {
+                                app.queue_items.unselect();
+                                app.set_input_mode(InputMode::Browser);
+                                app.browser_items.next();
+                            }
+                        },
+                        Action::CycleGrouping => app.library.cycle_grouping(),
+                        Action::Search => app.start_search(),
+                        Action::NextMatch => app.search_next(true),
+                        Action::PreviousMatch => app.search_next(false),
+                        Action::Find => app.open_finder(),
+                        Action::SavePlaylist => app.start_save_playlist(),
+                        Action::RenamePlaylist => app.rename_playlist(),
+                        Action::DeletePlaylist => app.delete_playlist(),
+                    }
                 }
             }
         }
@@ -194,8 +326,42 @@ fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App, cfg: &Config) {
 
     match app.active_tab {
         AppTab::Music => music_tab(f, app, chunks[1], cfg),
+        AppTab::Playlists => playlists_tab(f, app, chunks[1], cfg),
+        AppTab::Library => library_tab(f, app, chunks[1], cfg),
+        AppTab::History => history_tab(f, app, chunks[1], cfg),
         AppTab::Controls => instructions_tab(f, app, chunks[1], cfg),
     };
+
+    if app.finder().is_some() {
+        finder_popup(f, app, size, cfg);
+    }
+    match app.input_mode() {
+        InputMode::Confirm => {
+            let question = match app.deleting() {
+                Some(name) => format!("Delete the playlist {name}?\n(y)es / (n)o"),
+                None => format!(
+                    "Add {} songs to the queue?\n(y)es / (n)o",
+                    app.pending().len()
+                ),
+            };
+            popup(f, "| Confirm |", question, size, cfg);
+        }
+        InputMode::SavePlaylist => {
+            let title = match app.renaming() {
+                Some(_) => "| Rename Playlist To |",
+                None if app.saving_marked() => "| Save Marked Songs As |",
+                None => "| Save Queue As |",
+            };
+            let name = format!("{}_\nenter to save, esc to cancel", app.playlist_name());
+            popup(f, title, name, size, cfg);
+        }
+        _ => {
+            if let Some(notice) = app.notice() {
+                let notice = format!("{notice}\nany key to close");
+                popup(f, "| Notice |", notice, size, cfg);
+            }
+        }
+    }
 }
 
 fn music_tab<B: Backend>(f: &mut Frame<B>, app: &mut App, chunks: Rect, cfg: &Config) {
@@ -218,20 +384,46 @@ fn music_tab<B: Backend>(f: &mut Frame<B>, app: &mut App, chunks: Rect, cfg: &Co
         )
         .split(browser_queue[1]);
 
+    // while searching only the matches are listed
+    let search = app.searching(InputMode::Browser);
+    let browser_title = match search {
+        Some(search) => format!("Browser /{}", search.query()),
+        None => "Browser".to_string(),
+    };
+    let mut browser_state = match search {
+        Some(search) => {
+            let mut state = ListState::default();
+            state.select(search.current());
+            state
+        }
+        None => app.browser_items.state(),
+    };
+
     // convert app items to text
-    let items: Vec<ListItem> = app
-        .browser_items
-        .items()
-        .iter()
-        .map(|i| ListItem::new(Text::from(i.to_owned())))
-        .collect();
+    let items: Vec<ListItem> = match search {
+        Some(search) => search
+            .matches()
+            .iter()
+            .map(|&i| {
+                let marked = app.browser_items.is_marked(i);
+                list_item(app.browser_items.items()[i].to_owned(), marked, cfg)
+            })
+            .collect(),
+        None => app
+            .browser_items
+            .items()
+            .iter()
+            .enumerate()
+            .map(|(i, item)| list_item(item.to_owned(), app.browser_items.is_marked(i), cfg))
+            .collect(),
+    };
 
     // Create a List from all list items and highlight the currently selected one // RENDER 1
     let items = List::new(items)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title("Browser")
+                .title(browser_title)
                 .title_alignment(Alignment::Left)
                 .border_type(BorderType::Rounded),
         )
@@ -243,20 +435,47 @@ fn music_tab<B: Backend>(f: &mut Frame<B>, app: &mut App, chunks: Rect, cfg: &Co
                 .add_modifier(Modifier::BOLD),
         )
         .highlight_symbol(">> ");
-    f.render_stateful_widget(items, browser_queue[0], &mut app.browser_items.state());
+    f.render_stateful_widget(items, browser_queue[0], &mut browser_state);
 
-    let queue_items: Vec<ListItem> = app
-        .queue_items
-        .items()
-        .iter()
-        .map(|i| ListItem::new(Text::from(gen_funcs::audio_display(i))))
-        .collect();
+    let search = app.searching(InputMode::Queue);
+    let queue = app.queue_items.items();
+    let queue_items: Vec<ListItem> = match search {
+        Some(search) => search
+            .matches()
+            .iter()
+            .filter_map(|&i| {
+                let text = gen_funcs::audio_display(queue.get(i)?);
+                Some(list_item(text, app.queue_items.is_marked(i), cfg))
+            })
+            .collect(),
+        None => queue
+            .iter()
+            .enumerate()
+            .map(|(i, item)| {
+                let marked = app.queue_items.is_marked(i);
+                list_item(gen_funcs::audio_display(item), marked, cfg)
+            })
+            .collect(),
+    };
+    let mut queue_state = match search {
+        Some(search) => {
+            let mut state = ListState::default();
+            state.select(search.current());
+            state
+        }
+        None => app.queue_items.state(),
+    };
 
-    let queue_title = format!(
-        "| Queue: {queue_items} Songs |{total_time}",
-        queue_items = app.queue_items.length(),
-        total_time = app.queue_items.total_time(),
-    );
+    let queue_title = match search {
+        Some(search) => format!("| Queue /{} |", search.query()),
+        None => format!(
+            "| Queue: {queue_items} Songs |{total_time} Repeat: {repeat} | Shuffle: {shuffle} |",
+            queue_items = app.queue_items.length(),
+            total_time = app.queue_items.total_time(),
+            repeat = app.queue_items.repeat(),
+            shuffle = app.queue_items.shuffle_mode(),
+        ),
+    };
 
     let queue_items = List::new(queue_items)
         .block(
@@ -274,7 +493,7 @@ fn music_tab<B: Backend>(f: &mut Frame<B>, app: &mut App, chunks: Rect, cfg: &Co
                 .add_modifier(Modifier::BOLD),
         )
         .highlight_symbol(">> ");
-    f.render_stateful_widget(queue_items, queue_playing[0], &mut app.queue_items.state());
+    f.render_stateful_widget(queue_items, queue_playing[0], &mut queue_state);
 
     let playing_title = format!("| {current_song} |", current_song = app.current_song());
 
@@ -290,7 +509,321 @@ fn music_tab<B: Backend>(f: &mut Frame<B>, app: &mut App, chunks: Rect, cfg: &Co
         .style(Style::default().fg(cfg.foreground()))
         .gauge_style(Style::default().fg(cfg.highlight_background()))
         .ratio(app.song_progress());
-    f.render_widget(playing, queue_playing[1]);
+
+    // when playback is set to stop, how long is left is shown beside the song
+    let stopping = match app.sleep_left() {
+        Some(left) => Some(format!(
+            "Sleep in {}:{:02}",
+            left.as_secs() / 60,
+            left.as_secs() % 60
+        )),
+        None => app
+            .stop_after_current()
+            .then(|| "Stop after song".to_string()),
+    };
+    let playing_area = match stopping {
+        Some(stopping) => {
+            let playing_stopping = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(10), Constraint::Length(20)].as_ref())
+                .split(queue_playing[1]);
+
+            let title = match app.sleep_timer() {
+                SleepTimer::Off => "| Stopping |".to_string(),
+                timer => format!("| Sleep: {timer} |"),
+            };
+            let stopping = Paragraph::new(stopping)
+                .block(
+                    Block::default()
+                        .title(title)
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded)
+                        .title_alignment(Alignment::Center),
+                )
+                .alignment(Alignment::Center)
+                .style(Style::default().fg(cfg.foreground()));
+
+            f.render_widget(stopping, playing_stopping[1]);
+            playing_stopping[0]
+        }
+        None => queue_playing[1],
+    };
+    f.render_widget(playing, playing_area);
+    loop_markers(f, app, playing_area, cfg);
+}
+
+// points A and B of the loop, drawn over the progress bar where they fall in the song
+fn loop_markers<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect, cfg: &Config) {
+    let markers = match app.music_handle.ab_loop() {
+        AbLoop::Off => return,
+        AbLoop::A(a) => vec![("A", a)],
+        AbLoop::Looping { a, b } => vec![("A", a), ("B", b)],
+    };
+
+    let length = app.music_handle.song_length();
+    let gauge = Block::default().borders(Borders::ALL).inner(area);
+    if length.is_zero() || gauge.width == 0 || gauge.height == 0 {
+        return;
+    }
+
+    for (name, pos) in markers {
+        let ratio = (pos.as_secs_f64() / length.as_secs_f64()).clamp(0.0, 1.0);
+        let x = gauge.x + ((f64::from(gauge.width) * ratio) as u16).min(gauge.width - 1);
+        let mut lines = vec![Spans::from(name)];
+        lines.resize(gauge.height as usize, Spans::from("│"));
+
+        let marker = Paragraph::new(lines).style(
+            Style::default()
+                .fg(cfg.foreground())
+                .add_modifier(Modifier::BOLD),
+        );
+        f.render_widget(marker, Rect::new(x, gauge.y, 1, gauge.height));
+    }
+}
+
+// marked items stand out in the highlight color
+fn list_item<'a>(text: String, marked: bool, cfg: &Config) -> ListItem<'a> {
+    if marked {
+        ListItem::new(Text::from(format!("+ {text}"))).style(
+            Style::default()
+                .fg(cfg.highlight_background())
+                .add_modifier(Modifier::BOLD),
+        )
+    } else {
+        ListItem::new(Text::from(text))
+    }
+}
+
+fn playlists_tab<B: Backend>(f: &mut Frame<B>, app: &mut App, chunks: Rect, cfg: &Config) {
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(35), Constraint::Percentage(65)].as_ref())
+        .split(chunks);
+
+    let playlists = &app.playlists;
+    let songs_title = match playlists.selected_name() {
+        Some(name) => format!("| {name} |"),
+        None => "| Songs |".to_string(),
+    };
+    let songs = playlists
+        .songs()
+        .iter()
+        .map(|path| gen_funcs::audio_display(path));
+
+    let lists = [
+        (
+            "| Playlists |".to_string(),
+            playlists.names().to_vec(),
+            playlists.state(),
+        ),
+        (songs_title, songs.collect(), playlists.song_state()),
+    ];
+
+    for (column, (title, items, mut state)) in lists.into_iter().enumerate() {
+        let items: Vec<ListItem> = items
+            .into_iter()
+            .map(|i| ListItem::new(Text::from(i)))
+            .collect();
+
+        // only the focused column shows its selection highlighted
+        let highlight_style = if (column == 1) == playlists.editing() {
+            Style::default()
+                .bg(cfg.highlight_background())
+                .fg(cfg.highlight_foreground())
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().add_modifier(Modifier::BOLD)
+        };
+
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(title)
+                    .title_alignment(Alignment::Left)
+                    .border_type(BorderType::Rounded),
+            )
+            .style(Style::default().fg(cfg.foreground()))
+            .highlight_style(highlight_style)
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(list, columns[column], &mut state);
+    }
+}
+
+fn library_tab<B: Backend>(f: &mut Frame<B>, app: &mut App, chunks: Rect, cfg: &Config) {
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(
+            [
+                Constraint::Percentage(25),
+                Constraint::Percentage(30),
+                Constraint::Percentage(45),
+            ]
+            .as_ref(),
+        )
+        .split(chunks);
+
+    let library = &app.library;
+    let groups_title = if library.scanning() {
+        format!("| {} | Scanning... |", library.grouping())
+    } else {
+        format!("| {} |", library.grouping())
+    };
+    let tracks = library.tracks().map(|(path, song)| {
+        let title = song
+            .title
+            .clone()
+            .unwrap_or_else(|| path.file_name().unwrap().to_string_lossy().to_string());
+        match song.track {
+            Some(track) => format!("{track:02}. {title}"),
+            None => title,
+        }
+    });
+
+    let lists = [
+        (groups_title, library.groups().to_vec()),
+        ("| Albums |".to_string(), library.albums().to_vec()),
+        ("| Tracks |".to_string(), tracks.collect()),
+    ];
+
+    for (column, (title, items)) in lists.into_iter().enumerate() {
+        let items: Vec<ListItem> = items
+            .into_iter()
+            .map(|i| ListItem::new(Text::from(i)))
+            .collect();
+
+        // only the focused column shows its selection highlighted
+        let highlight_style = if column == library.focus() {
+            Style::default()
+                .bg(cfg.highlight_background())
+                .fg(cfg.highlight_foreground())
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().add_modifier(Modifier::BOLD)
+        };
+
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(title)
+                    .title_alignment(Alignment::Left)
+                    .border_type(BorderType::Rounded),
+            )
+            .style(Style::default().fg(cfg.foreground()))
+            .highlight_style(highlight_style)
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(list, columns[column], &mut library.state(column));
+    }
+}
+
+fn history_tab<B: Backend>(f: &mut Frame<B>, app: &mut App, chunks: Rect, cfg: &Config) {
+    let items: Vec<ListItem> = app
+        .history
+        .items()
+        .into_iter()
+        .map(|i| ListItem::new(Text::from(i)))
+        .collect();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("| History |")
+                .title_alignment(Alignment::Left)
+                .border_type(BorderType::Rounded),
+        )
+        .style(Style::default().fg(cfg.foreground()))
+        .highlight_style(
+            Style::default()
+                .bg(cfg.highlight_background())
+                .fg(cfg.highlight_foreground())
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol(">> ");
+    f.render_stateful_widget(list, chunks, &mut app.history.state());
+}
+
+fn finder_popup<B: Backend>(f: &mut Frame<B>, app: &App, size: Rect, cfg: &Config) {
+    let Some(finder) = app.finder() else {
+        return;
+    };
+    let songs = app.library.songs();
+
+    // centered, covering most of the screen
+    let area = Rect {
+        x: size.width / 8,
+        y: size.height / 8,
+        width: size.width * 3 / 4,
+        height: size.height * 3 / 4,
+    };
+
+    let results: Vec<ListItem> = finder
+        .results()
+        .iter()
+        .map(|&i| {
+            let (path, song) = &songs[i];
+            ListItem::new(Spans::from(vec![
+                Span::raw(gen_funcs::song_display(path, Some(song))),
+                Span::styled(
+                    format!("  {}", path.display()),
+                    Style::default().add_modifier(Modifier::DIM),
+                ),
+            ]))
+        })
+        .collect();
+
+    let title = format!(
+        "| Find: {query} | {results} of {songs} |",
+        query = finder.query(),
+        results = finder.results().len(),
+        songs = songs.len(),
+    );
+
+    let results = List::new(results)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .title_alignment(Alignment::Left)
+                .border_type(BorderType::Rounded),
+        )
+        .style(Style::default().fg(cfg.foreground()).bg(cfg.background()))
+        .highlight_style(
+            Style::default()
+                .bg(cfg.highlight_background())
+                .fg(cfg.highlight_foreground())
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol(">> ");
+
+    let mut state = ListState::default();
+    state.select(finder.current());
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(results, area, &mut state);
+}
+
+// small box in the middle of the screen with two lines of text
+fn popup<B: Backend>(f: &mut Frame<B>, title: &str, text: String, size: Rect, cfg: &Config) {
+    let area = Rect {
+        x: size.width / 4,
+        y: size.height.saturating_sub(4) / 2,
+        width: size.width / 2,
+        height: 4.min(size.height),
+    };
+
+    let text = Paragraph::new(text)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .border_type(BorderType::Rounded),
+        )
+        .alignment(Alignment::Center)
+        .style(Style::default().fg(cfg.foreground()).bg(cfg.background()));
+
+    f.render_widget(Clear, area);
+    f.render_widget(text, area);
 }
 
 fn instructions_tab<B: Backend>(f: &mut Frame<B>, app: &mut App, chunks: Rect, cfg: &Config) {
@@ -321,7 +854,7 @@ fn instructions_tab<B: Backend>(f: &mut Frame<B>, app: &mut App, chunks: Rect, c
             .max()
             .unwrap_or(0)
             + 1;
-        let cells = item.iter().map(|c| Cell::from(*c));
+        let cells = item.iter().map(|c| Cell::from(c.as_str()));
         Row::new(cells).height(height as u16).bottom_margin(1)
     });
 
@@ -337,7 +870,7 @@ fn instructions_tab<B: Backend>(f: &mut Frame<B>, app: &mut App, chunks: Rect, c
         )
         // .highlight_symbol(">> ")
         .widths(&[
-            Constraint::Percentage(50),
+            Constraint::Length(12),
             Constraint::Length(30),
             Constraint::Min(10),
         ]);
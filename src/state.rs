@@ -1,10 +1,20 @@
 use std::fs;
 
+use kronos::queue::{RepeatMode, ShuffleMode};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug)]
+// missing fields fall back to defaults so state files from older versions still load
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
 pub struct State {
     pub last_visited_path: Option<String>,
+    pub queue: Vec<String>,
+    pub current_song: Option<String>,
+    /// How far into `current_song` playback was, in milliseconds.
+    pub position_ms: u64,
+    pub volume: Option<f32>,
+    pub repeat: RepeatMode,
+    pub shuffle: ShuffleMode,
 }
 
 pub fn load_state() -> State {
@@ -26,9 +36,7 @@ pub fn load_state() -> State {
 
     let state_toml: State = toml::from_str(&content).unwrap_or_else(|_| {
         eprintln!("FAILED TO CREATE STATE OBJECT FROM FILE");
-        State {
-            last_visited_path: None,
-        }
+        State::default()
     });
 
     state_toml